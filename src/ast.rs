@@ -1,23 +1,75 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
 
+// A statement remembers the line it starts on, so runtime errors can point at it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub line: usize,
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement {
+pub enum StatementKind {
     Let(LetStatement),
+    Const(ConstStatement),
     Show(ShowStatement),
     When(WhenStatement),
     FunctionDef(FunctionDef),
+    Return(ReturnStatement),
+    RepeatWhile(RepeatWhileStatement),
+    RepeatRange(RepeatRangeStatement),
+    RepeatEach(RepeatEachStatement),
+    Add(AddStatement),
+    Remove(RemoveStatement),
+    Choose(ChooseStatement),
+    Attempt(AttemptStatement),
+    Fail(FailStatement),
+    Import(ImportStatement),
+    Break,
+    Continue,
     Expression(Expression),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct LetStatement {
-    pub identifier: String,
+    pub targets: Vec<LetTarget>,
+    pub value: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstStatement {
+    pub name: String,
     pub value: Expression,
 }
 
+// What a `let` binds: a plain name, or a property such as `user.age`
+#[derive(Debug, Clone, PartialEq)]
+pub enum LetTarget {
+    Identifier(String),
+    Property(PropertyAccess),
+}
+
+impl LetTarget {
+    // The variable a target ultimately writes into: `user` for `let user.address.city be ...`
+    pub fn root_name(&self) -> Option<&str> {
+        let mut expression = match self {
+            LetTarget::Identifier(name) => return Some(name),
+            LetTarget::Property(access) => &*access.object,
+        };
+        loop {
+            match expression {
+                Expression::Identifier(name) => return Some(name),
+                Expression::Property(access) => expression = &access.object,
+                _ => return None,
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ShowStatement {
     pub value: Expression,
@@ -30,20 +82,193 @@ pub struct WhenStatement {
     pub otherwise_block: Option<Vec<Statement>>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatWhileStatement {
+    pub condition: Expression,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatRangeStatement {
+    pub variable: String,
+    pub start: Expression,
+    pub end: Expression,
+    pub step: Option<Expression>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatEachStatement {
+    pub variable: String,
+    pub iterable: Expression,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddStatement {
+    pub item: Expression,
+    pub list: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoveStatement {
+    pub item: Expression,
+    pub list: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChooseStatement {
+    pub subject: Expression,
+    pub arms: Vec<ChooseArm>,
+    pub otherwise_block: Option<Vec<Statement>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChooseArm {
+    pub pattern: ChoosePattern,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChoosePattern {
+    Value(Expression),
+    // Inclusive on both ends: `when 80 to 89`
+    Range(Expression, Expression),
+}
+
+// `attempt` needs a `rescue` block, an `always` block, or both
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttemptStatement {
+    pub body: Vec<Statement>,
+    pub rescue: Option<RescueClause>,
+    pub always_block: Option<Vec<Statement>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RescueClause {
+    // The name the caught error is bound to, if any: `rescue problem`
+    pub variable: Option<String>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailStatement {
+    pub value: Expression,
+}
+
+// `import "math"`, `import "math" as m` or `import power, floor from "math"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportStatement {
+    pub module: String,
+    pub alias: Option<String>,
+    // Names to bind directly; empty when the whole module is imported
+    pub names: Vec<ImportedName>,
+}
+
+// `power` or `power as raise` in `import ... from "math"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedName {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl ImportedName {
+    // The name the value is bound to in the importing module
+    pub fn bound(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionDef {
     pub name: String,
-    pub parameters: Vec<String>,
+    pub parameters: Vec<Parameter>,
     pub body: Vec<Statement>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Option<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnStatement {
+    pub values: Vec<Expression>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Number(f64),
     String(String),
+    Interpolation(Vec<InterpolationPart>),
+    Boolean(bool),
+    Nothing,
     Identifier(String),
+    List(Vec<Expression>),
+    ListQuery(ListQuery),
+    Object(Vec<(String, Expression)>),
+    Property(PropertyAccess),
     BinaryOp(BinaryOperation),
+    Logical(LogicalOperation),
+    Not(Box<Expression>),
+    Negate(Box<Expression>),
     FunctionCall(FunctionCall),
+    Ask(Ask),
+}
+
+// A piece of `"Total: {price:.2}"`
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpolationPart {
+    Text(String),
+    Value {
+        expression: Expression,
+        format: Option<FormatSpec>,
+    },
+}
+
+// `[[fill]align][width][.precision][%]`, e.g. `.2`, `>5`, `*^9`, `.1%`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<Alignment>,
+    pub width: usize,
+    pub precision: Option<usize>,
+    pub percent: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyAccess {
+    pub object: Box<Expression>,
+    pub property: String,
+}
+
+// `length of`, `first of` and `last of`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQuery {
+    pub kind: ListQueryKind,
+    pub list: Box<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListQueryKind {
+    Length,
+    First,
+    Last,
+}
+
+// `ask "Name: "` reads a line of text; `ask number "Age: "` reads a number
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ask {
+    pub numeric: bool,
+    pub prompt: Option<Box<Expression>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -61,14 +286,78 @@ pub enum BinaryOperator {
     LessThanOrEqual,
     Equal,
     NotEqual,
+    Contains,
+    StartsWith,
+    EndsWith,
     Add,
     Subtract,
     Multiply,
     Divide,
+    Modulo,
+    Power,
+}
+
+// `and` / `or`, kept apart from BinaryOperation because the right side is only
+// evaluated when the left side does not already decide the result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalOperation {
+    pub left: Box<Expression>,
+    pub operator: LogicalOperator,
+    pub right: Box<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicalOperator {
+    And,
+    Or,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionCall {
-    pub name: String,
+    // A name, or a property such as `math.power`
+    pub callee: Box<Expression>,
     pub arguments: Vec<Expression>,
-}
\ No newline at end of file
+}
+
+impl fmt::Display for ListQueryKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            ListQueryKind::Length => "length",
+            ListQueryKind::First => "first",
+            ListQueryKind::Last => "last",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl fmt::Display for LogicalOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogicalOperator::And => write!(f, "and"),
+            LogicalOperator::Or => write!(f, "or"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            BinaryOperator::GreaterThan => "is greater than",
+            BinaryOperator::LessThan => "is less than",
+            BinaryOperator::GreaterThanOrEqual => "is greater than or equal",
+            BinaryOperator::LessThanOrEqual => "is less than or equal",
+            BinaryOperator::Equal => "is equal",
+            BinaryOperator::NotEqual => "is not equal",
+            BinaryOperator::Contains => "contains",
+            BinaryOperator::StartsWith => "starts with",
+            BinaryOperator::EndsWith => "ends with",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Power => "^",
+        };
+        write!(f, "{}", text)
+    }
+}
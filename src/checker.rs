@@ -0,0 +1,290 @@
+use crate::ast::*;
+use crate::modules;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// Static checks that run between parsing and interpreting. Warnings are only
+// reported; errors stop the program before it starts.
+pub struct Checker {
+    warnings: Vec<String>,
+    errors: Vec<String>,
+    // Constants declared so far, with the line of their declaration
+    constants: HashMap<String, usize>,
+    // Names a whole module is bound to, whose properties cannot be set
+    modules: HashSet<String>,
+}
+
+// A `choose` arm pattern whose bounds are known before the program runs.
+#[derive(Clone)]
+enum Literal {
+    Number(f64),
+    String(String),
+}
+
+impl Literal {
+    fn from_expression(expression: &Expression) -> Option<Literal> {
+        match expression {
+            Expression::Number(n) => Some(Literal::Number(*n)),
+            Expression::Negate(operand) => match **operand {
+                Expression::Number(n) => Some(Literal::Number(-n)),
+                _ => None,
+            },
+            Expression::String(s) => Some(Literal::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    // Literals of different types are never compared.
+    fn compare(&self, other: &Literal) -> Option<Ordering> {
+        match (self, other) {
+            (Literal::Number(a), Literal::Number(b)) => a.partial_cmp(b),
+            (Literal::String(a), Literal::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Literal::Number(n) => n.to_string(),
+            Literal::String(s) => format!("\"{}\"", s),
+        }
+    }
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            constants: HashMap::new(),
+            modules: HashSet::new(),
+        }
+    }
+
+    pub fn check(&mut self, program: &Program) {
+        self.check_block(&program.statements);
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    // A constant's name cannot be rebound anywhere, even where the new binding would
+    // only be local.
+    fn check_binding(&mut self, name: &str, role: &str, line: usize) {
+        if let Some(declared) = self.constants.get(name) {
+            self.errors.push(format!(
+                "Cannot use constant '{}' as {} at line {} (declared at line {})",
+                name, role, line, declared
+            ));
+        }
+    }
+
+    fn check_block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::When(when_stmt) => {
+                self.check_block(&when_stmt.then_block);
+                if let Some(otherwise_block) = &when_stmt.otherwise_block {
+                    self.check_block(otherwise_block);
+                }
+            }
+            StatementKind::FunctionDef(func_def) => {
+                for parameter in &func_def.parameters {
+                    self.check_binding(&parameter.name, "a parameter", statement.line);
+                }
+                self.check_block(&func_def.body);
+            }
+            StatementKind::RepeatWhile(repeat) => self.check_block(&repeat.body),
+            StatementKind::RepeatRange(repeat) => {
+                self.check_binding(&repeat.variable, "a loop variable", statement.line);
+                self.check_block(&repeat.body);
+            }
+            StatementKind::RepeatEach(repeat) => {
+                self.check_binding(&repeat.variable, "a loop variable", statement.line);
+                self.check_block(&repeat.body);
+            }
+            StatementKind::Choose(choose) => {
+                self.check_choose_arms(&choose.arms);
+                for arm in &choose.arms {
+                    self.check_block(&arm.body);
+                }
+                if let Some(otherwise_block) = &choose.otherwise_block {
+                    self.check_block(otherwise_block);
+                }
+            }
+            StatementKind::Attempt(attempt) => {
+                self.check_block(&attempt.body);
+                if let Some(rescue) = &attempt.rescue {
+                    if let Some(variable) = &rescue.variable {
+                        self.check_binding(variable, "the name of a rescued error", statement.line);
+                    }
+                    self.check_block(&rescue.body);
+                }
+                if let Some(always_block) = &attempt.always_block {
+                    self.check_block(always_block);
+                }
+            }
+            StatementKind::Const(const_stmt) => {
+                if let Some(declared) = self.constants.get(&const_stmt.name) {
+                    self.errors.push(format!(
+                        "Constant '{}' at line {} is already declared at line {}",
+                        const_stmt.name, statement.line, declared
+                    ));
+                } else {
+                    self.constants.insert(const_stmt.name.clone(), statement.line);
+                }
+            }
+            StatementKind::Let(let_stmt) => {
+                for target in &let_stmt.targets {
+                    let declared = target.root_name().and_then(|name| self.constants.get(name));
+                    if let Some(declared) = declared {
+                        self.errors.push(format!(
+                            "Cannot assign to constant '{}' at line {} (declared at line {})",
+                            target.root_name().unwrap_or_default(),
+                            statement.line,
+                            declared
+                        ));
+                    }
+                    match target {
+                        LetTarget::Property(access) => {
+                            if let Expression::Identifier(name) = &*access.object
+                                && self.modules.contains(name)
+                            {
+                                self.errors.push(format!(
+                                    "Cannot set '{}' on imported module '{}' at line {}",
+                                    access.property, name, statement.line
+                                ));
+                            }
+                        }
+                        LetTarget::Identifier(name) => {
+                            self.modules.remove(name);
+                        }
+                    }
+                }
+            }
+            StatementKind::Import(import) => {
+                let bound = if import.names.is_empty() {
+                    let name = import.alias.clone().unwrap_or_else(|| modules::default_name(&import.module));
+                    self.modules.insert(name.clone());
+                    vec![name]
+                } else {
+                    import.names.iter().map(|name| name.bound().to_string()).collect()
+                };
+                for name in bound {
+                    if let Some(declared) = self.constants.get(&name) {
+                        self.errors.push(format!(
+                            "Cannot import '{}' over a constant at line {} (declared at line {})",
+                            name, statement.line, declared
+                        ));
+                    }
+                }
+            }
+            StatementKind::Show(_)
+            | StatementKind::Return(_)
+            | StatementKind::Break
+            | StatementKind::Continue
+            | StatementKind::Add(_)
+            | StatementKind::Remove(_)
+            | StatementKind::Fail(_)
+            | StatementKind::Expression(_) => {}
+        }
+    }
+
+    // Arms are tried top to bottom, so an arm is unreachable when an earlier arm
+    // already covers everything it matches. Only literal patterns are checked.
+    fn check_choose_arms(&mut self, arms: &[ChooseArm]) {
+        let mut earlier: Vec<(Literal, Literal)> = Vec::new();
+
+        for arm in arms {
+            let (low, high, text) = match &arm.pattern {
+                ChoosePattern::Value(value) => match Literal::from_expression(value) {
+                    Some(value) => (value.clone(), value.clone(), format!("when {}", value.describe())),
+                    None => continue,
+                },
+                ChoosePattern::Range(low, high) => {
+                    match (Literal::from_expression(low), Literal::from_expression(high)) {
+                        (Some(low), Some(high)) => {
+                            let text = format!("when {} to {}", low.describe(), high.describe());
+                            (low, high, text)
+                        }
+                        _ => continue,
+                    }
+                }
+            };
+
+            if low.compare(&high) == Some(Ordering::Greater) {
+                self.warnings.push(format!("choose arm '{}' can never match: the range is empty", text));
+                continue;
+            }
+
+            let covered = earlier.iter().any(|(earlier_low, earlier_high)| {
+                matches!(earlier_low.compare(&low), Some(Ordering::Less | Ordering::Equal))
+                    && matches!(high.compare(earlier_high), Some(Ordering::Less | Ordering::Equal))
+            });
+            let overlaps = earlier.iter().any(|(earlier_low, earlier_high)| {
+                matches!(earlier_low.compare(&high), Some(Ordering::Less | Ordering::Equal))
+                    && matches!(low.compare(earlier_high), Some(Ordering::Less | Ordering::Equal))
+            });
+
+            if covered {
+                self.warnings.push(format!(
+                    "choose arm '{}' is unreachable: an earlier arm already matches all of its values",
+                    text
+                ));
+            } else if overlaps {
+                self.warnings.push(format!("choose arm '{}' overlaps an earlier arm", text));
+            }
+
+            earlier.push((low, high));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn errors(source: &str) -> Vec<String> {
+        let program = Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+        let mut checker = Checker::new();
+        checker.check(&program);
+        checker.errors().to_vec()
+    }
+
+    #[test]
+    fn properties_of_imported_modules_cannot_be_set() {
+        assert_eq!(errors("import \"math\"\nlet math.pi be 3\n"), ["Cannot set 'pi' on imported module 'math' at line 2"]);
+        assert_eq!(errors("import \"math\" as m\nlet m.pi be 3\n").len(), 1);
+        // Once the name holds something else, its properties are that value's
+        assert!(errors("import \"math\"\nlet math be {pi: 3}\nlet math.pi be 4\n").is_empty());
+    }
+
+    #[test]
+    fn constants_cannot_be_rebound_even_locally() {
+        let declared = "const PI be 3.14\n";
+        let cases = [
+            ("define f\n    let PI be 3\n    return PI\n", "Cannot assign to constant 'PI' at line 3 (declared at line 1)"),
+            ("define g with PI\n    return PI\n", "Cannot use constant 'PI' as a parameter at line 2 (declared at line 1)"),
+            ("repeat for each PI from 1 to 3\n    show PI\n", "Cannot use constant 'PI' as a loop variable at line 2 (declared at line 1)"),
+            (
+                "attempt\n    show 1\nrescue PI\n    show PI\n",
+                "Cannot use constant 'PI' as the name of a rescued error at line 2 (declared at line 1)",
+            ),
+        ];
+        for (source, error) in cases {
+            assert_eq!(errors(&format!("{}{}", declared, source)), [error]);
+        }
+    }
+}
@@ -1,107 +1,1183 @@
 use crate::ast::*;
+use crate::checker::Checker;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::lexer::Lexer;
+use crate::modules::{self, ModuleSource};
+use crate::parser::Parser;
+use crate::library::Host;
+use crate::library::date_time::Duration;
+use crate::value::{Builtin, Function, Module, Object, Value};
+use std::cmp::Ordering;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Deep enough for ordinary recursion. The interpreter runs on a thread with a
+// `STACK_SIZE` stack, which holds this many calls even in a debug build.
+const MAX_CALL_DEPTH: usize = 1000;
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// How a statement finished, so enclosing blocks know whether to keep going.
+enum ControlFlow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+type Scope = HashMap<String, Value>;
+
+// A module file whose top level is still running.
+struct LoadingModule {
+    // The canonical path, which identifies the module in the cache
+    key: String,
+    // The file name shown in a cyclic import error
+    name: String,
+    // Where its own imports are looked up first
+    dir: PathBuf,
+}
+
+impl LoadingModule {
+    fn new(path: &Path) -> Self {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        LoadingModule {
+            key: key.to_string_lossy().into_owned(),
+            name: path.file_name().map_or_else(|| key.to_string_lossy(), |name| name.to_string_lossy()).into_owned(),
+            dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        }
+    }
+}
 
 pub struct CodeGenerator {
-    variables: HashMap<String, String>,
+    // Globals and constants of the module whose code is running
+    module: Rc<RefCell<Module>>,
+    // One frame per active function call, plus the top level at the bottom. Each frame
+    // is a stack of scopes, innermost last; a call starts with one scope for its
+    // parameters and locals, while the top level keeps its variables in `module`.
+    frames: Vec<Vec<Scope>>,
+    // Where `ask` reads its answers from
+    input: Box<dyn BufRead>,
+    // Namespaces of imported modules, so each one only runs once
+    loaded_modules: HashMap<String, Rc<RefCell<Object>>>,
+    // The program file, then each module it is in the middle of importing
+    importing: Vec<LoadingModule>,
+    // State and settings shared by the built-in libraries
+    host: Host,
 }
 
 impl CodeGenerator {
-    pub fn new() -> Self {
+    pub fn new(host: Host) -> Self {
+        CodeGenerator::with_input(host, Box::new(BufReader::new(io::stdin())))
+    }
+
+    // Reads `ask` answers from `input` instead of the terminal, e.g. canned answers in tests.
+    pub fn with_input(host: Host, input: Box<dyn BufRead>) -> Self {
         CodeGenerator {
-            variables: HashMap::new(),
+            module: Rc::new(RefCell::new(Module::default())),
+            frames: vec![Vec::new()],
+            input,
+            loaded_modules: HashMap::new(),
+            importing: Vec::new(),
+            host,
         }
     }
-    
+
+    #[allow(dead_code)]
     pub fn generate(&mut self, _program: &Program) -> Result<(), String> {
         // TODO: Add LLVM IR Generation Code.
         // For now, we wil just interpret the AST.
         println!("Code generation not yet implemented - use interpreter");
         Ok(())
     }
-    
-    pub fn interpret(&mut self, program: &Program) -> Result<(), String> {
-        for statement in &program.statements {
-            self.interpret_statement(statement)?;
-        }
+
+    // `path` is the program's own file; imports are looked up next to it.
+    pub fn interpret(&mut self, program: &Program, path: &Path) -> Result<(), RuntimeError> {
+        self.importing.push(LoadingModule::new(path));
+        self.execute_block(&program.statements)?;
         Ok(())
     }
-    
-    fn interpret_statement(&mut self, statement: &Statement) -> Result<(), String> {
-        match statement {
-            Statement::Show(show) => {
+
+    fn execute_block(&mut self, statements: &[Statement]) -> Result<ControlFlow, RuntimeError> {
+        for statement in statements {
+            let flow = self.interpret_statement(statement)?;
+            if !matches!(flow, ControlFlow::Next) {
+                return Ok(flow);
+            }
+        }
+        Ok(ControlFlow::Next)
+    }
+
+    // Errors leave a statement carrying its line and module, unless an inner statement
+    // already set them.
+    fn interpret_statement(&mut self, statement: &Statement) -> Result<ControlFlow, RuntimeError> {
+        self.execute_statement(statement)
+            .map_err(|error| error.at_line(statement.line, self.module.borrow().file.as_deref()))
+    }
+
+    fn execute_statement(&mut self, statement: &Statement) -> Result<ControlFlow, RuntimeError> {
+        match &statement.kind {
+            StatementKind::Show(show) => {
                 let value = self.evaluate_expression(&show.value)?;
                 println!("{}", value);
             }
-            Statement::Let(let_stmt) => {
+            StatementKind::Let(let_stmt) => {
                 let value = self.evaluate_expression(&let_stmt.value)?;
-                self.variables.insert(let_stmt.identifier.clone(), value);
-            }
-            Statement::When(when_stmt) => {
-                let condition_result = self.evaluate_expression(&when_stmt.condition)?;
-                // For now, simple string-based evaluation
-                if condition_result.contains("true") || condition_result.contains("True") {
-                    for stmt in &when_stmt.then_block {
-                        self.interpret_statement(stmt)?;
+                for target in &let_stmt.targets {
+                    self.check_not_constant(target.root_name().unwrap_or_default(), None)?;
+                }
+                if let [target] = let_stmt.targets.as_slice() {
+                    self.assign(target, value)?;
+                } else {
+                    let values = Self::unpack(value, let_stmt.targets.len())?;
+                    for (target, value) in let_stmt.targets.iter().zip(values) {
+                        self.assign(target, value)?;
                     }
+                }
+            }
+            StatementKind::Const(const_stmt) => {
+                if let Some(declared) = self.module.borrow().constants.get(&const_stmt.name) {
+                    return Err(RuntimeError::new(
+                        ErrorKind::Constant,
+                        format!("Cannot redeclare constant '{}' (declared at line {})", const_stmt.name, declared),
+                    ));
+                }
+                if self.lookup_variable(&const_stmt.name).is_some() {
+                    return Err(RuntimeError::new(
+                        ErrorKind::Constant,
+                        format!(
+                            "Cannot declare constant '{}': a variable with that name already exists",
+                            const_stmt.name
+                        ),
+                    ));
+                }
+                let value = self.evaluate_expression(&const_stmt.value)?;
+                let mut module = self.module.borrow_mut();
+                module.constants.insert(const_stmt.name.clone(), statement.line);
+                module.variables.insert(const_stmt.name.clone(), value);
+            }
+            StatementKind::When(when_stmt) => {
+                let condition = self.evaluate_expression(&when_stmt.condition)?;
+                if Self::is_true(&condition)? {
+                    return self.execute_block(&when_stmt.then_block);
                 } else if let Some(otherwise_block) = &when_stmt.otherwise_block {
-                    for stmt in otherwise_block {
-                        self.interpret_statement(stmt)?;
+                    return self.execute_block(otherwise_block);
+                }
+            }
+            StatementKind::FunctionDef(func_def) => {
+                let function = Function {
+                    def: func_def.clone(),
+                    module: Rc::clone(&self.module),
+                };
+                self.set_variable(&func_def.name, Value::Function(Rc::new(function)));
+            }
+            StatementKind::Import(import) => {
+                let namespace = self.import_module(&import.module)?;
+                let mut bindings = Vec::new();
+                if import.names.is_empty() {
+                    let name = import.alias.clone().unwrap_or_else(|| modules::default_name(&import.module));
+                    bindings.push((name, Value::Object(namespace)));
+                } else {
+                    for name in &import.names {
+                        let value = namespace.borrow().get(&name.name).cloned().ok_or_else(|| {
+                            RuntimeError::new(
+                                ErrorKind::Import,
+                                format!("Module '{}' has no '{}'", import.module, name.name),
+                            )
+                        })?;
+                        bindings.push((name.bound().to_string(), value));
+                    }
+                }
+                for (name, value) in bindings {
+                    if let Some(declared) = self.module.borrow().constants.get(&name) {
+                        return Err(RuntimeError::new(
+                            ErrorKind::Constant,
+                            format!("Cannot import '{}' over a constant (declared at line {})", name, declared),
+                        ));
+                    }
+                    self.set_variable(&name, value);
+                }
+            }
+            StatementKind::Return(return_stmt) => {
+                let mut values = Vec::new();
+                for expr in &return_stmt.values {
+                    values.push(self.evaluate_expression(expr)?);
+                }
+                // Several return values travel back to the caller as a list
+                let value = match values.len() {
+                    0 => Value::Nothing,
+                    1 => values.pop().unwrap(),
+                    _ => Value::list(values),
+                };
+                return Ok(ControlFlow::Return(value));
+            }
+            StatementKind::RepeatWhile(repeat) => {
+                loop {
+                    let condition = self.evaluate_expression(&repeat.condition)?;
+                    if !Self::is_true(&condition)? {
+                        break;
+                    }
+                    match self.execute_block(&repeat.body)? {
+                        ControlFlow::Break => break,
+                        ControlFlow::Next | ControlFlow::Continue => {}
+                        flow @ ControlFlow::Return(_) => return Ok(flow),
+                    }
+                }
+            }
+            StatementKind::RepeatRange(repeat) => {
+                let start = self.evaluate_expression(&repeat.start)?;
+                let end = self.evaluate_expression(&repeat.end)?;
+                let step = match &repeat.step {
+                    Some(step) => Some(self.evaluate_expression(step)?),
+                    None => None,
+                };
+                let values = Self::range_values(&start, &end, step.as_ref())?;
+                return self.run_loop_body(&repeat.variable, values, &repeat.body);
+            }
+            StatementKind::RepeatEach(repeat) => {
+                // Iterate over a snapshot so the body may change the list safely
+                let items = match self.evaluate_expression(&repeat.iterable)? {
+                    Value::List(items) => items.borrow().clone(),
+                    Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
+                    other => {
+                        return Err(RuntimeError::new(
+                            ErrorKind::Type,
+                            format!("Type error: cannot repeat for each item in a {}", other.type_name()),
+                        ))
+                    }
+                };
+                return self.run_loop_body(&repeat.variable, items.into_iter(), &repeat.body);
+            }
+            StatementKind::Add(add) => {
+                let item = self.evaluate_expression(&add.item)?;
+                let list = self.evaluate_list(&add.list, "add to")?;
+                list.borrow_mut().push(item);
+            }
+            StatementKind::Remove(remove) => {
+                let item = self.evaluate_expression(&remove.item)?;
+                let list = self.evaluate_list(&remove.list, "remove from")?;
+                let position = list.borrow().iter().position(|existing| *existing == item);
+                match position {
+                    Some(index) => {
+                        list.borrow_mut().remove(index);
+                    }
+                    None => {
+                        return Err(RuntimeError::new(
+                            ErrorKind::Value,
+                            format!("Cannot remove {}: it is not in the list", item.repr()),
+                        ))
                     }
                 }
             }
-            Statement::FunctionDef(func_def) => {
-                println!("Defined function: {}", func_def.name);
+            StatementKind::Choose(choose) => {
+                let subject = self.evaluate_expression(&choose.subject)?;
+                for arm in &choose.arms {
+                    if self.arm_matches(&arm.pattern, &subject)? {
+                        return self.execute_block(&arm.body);
+                    }
+                }
+                if let Some(otherwise_block) = &choose.otherwise_block {
+                    return self.execute_block(otherwise_block);
+                }
             }
-            Statement::Expression(expr) => {
+            StatementKind::Attempt(attempt) => {
+                let mut result = self.execute_block(&attempt.body);
+                if let (Err(error), Some(rescue)) = (&result, &attempt.rescue)
+                    && !matches!(error.kind, ErrorKind::Exit(_))
+                {
+                    let error = Value::Error(Rc::new(error.clone()));
+                    result = match &rescue.variable {
+                        Some(variable) => self.execute_with_binding(variable, error, &rescue.body),
+                        None => self.execute_block(&rescue.body),
+                    };
+                }
+                // `always` runs however the block finished; its own error or jump wins
+                if let Some(always_block) = &attempt.always_block {
+                    match self.execute_block(always_block)? {
+                        ControlFlow::Next => {}
+                        flow => return Ok(flow),
+                    }
+                }
+                return result;
+            }
+            StatementKind::Fail(fail) => {
+                return match self.evaluate_expression(&fail.value)? {
+                    // Failing with a rescued error raises it again, unchanged
+                    Value::Error(error) => Err((*error).clone()),
+                    value => Err(RuntimeError::new(ErrorKind::Failure, value.to_string())),
+                };
+            }
+            StatementKind::Break => return Ok(ControlFlow::Break),
+            StatementKind::Continue => return Ok(ControlFlow::Continue),
+            StatementKind::Expression(expr) => {
                 let _value = self.evaluate_expression(expr)?;
                 // Expression statements don't print by default
             }
         }
+        Ok(ControlFlow::Next)
+    }
+
+    fn current_frame(&mut self) -> &mut Vec<Scope> {
+        self.frames.last_mut().expect("the top-level frame is never popped")
+    }
+
+    // Scopes of the innermost call shadow globals.
+    fn lookup_variable(&self, name: &str) -> Option<Value> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.iter().rev().find_map(|scope| scope.get(name)))
+            .cloned()
+            .or_else(|| self.module.borrow().variables.get(name).cloned())
+    }
+
+    // `let` updates the innermost scope that already has the name. Otherwise it binds a
+    // local inside a function and a global at the top level, never a loop-only variable.
+    fn set_variable(&mut self, name: &str, value: Value) {
+        let in_function = self.frames.len() > 1;
+        let frame = self.current_frame();
+        if let Some(scope) = frame.iter_mut().rev().find(|scope| scope.contains_key(name)) {
+            scope.insert(name.to_string(), value);
+        } else if in_function {
+            frame[0].insert(name.to_string(), value);
+        } else {
+            self.module.borrow_mut().variables.insert(name.to_string(), value);
+        }
+    }
+
+    // A constant's name cannot be bound to anything else anywhere in its module: not by
+    // `let`, even inside a function, nor as a parameter, loop variable or `rescue` name.
+    // The checker rejects most of these; this catches the rest, such as a function
+    // defined before the constant it later tries to overwrite.
+    // `role` names what the binding would make it, or is `None` for an assignment.
+    fn check_not_constant(&self, name: &str, role: Option<&str>) -> Result<(), RuntimeError> {
+        let module = self.module.borrow();
+        let Some(declared) = module.constants.get(name) else {
+            return Ok(());
+        };
+        let message = match role {
+            Some(role) => format!("Cannot use constant '{}' as {} (declared at line {})", name, role, declared),
+            None => format!("Cannot assign to constant '{}' (declared at line {})", name, declared),
+        };
+        Err(RuntimeError::new(ErrorKind::Constant, message))
+    }
+
+    // Runs `body` once per value, with `variable` visible only inside the body.
+    fn run_loop_body<I>(&mut self, variable: &str, values: I, body: &[Statement]) -> Result<ControlFlow, RuntimeError>
+    where
+        I: Iterator<Item = Value>,
+    {
+        self.check_not_constant(variable, Some("a loop variable"))?;
+        self.current_frame().push(HashMap::new());
+        let mut result = Ok(ControlFlow::Next);
+        for value in values {
+            let scope = self.current_frame().last_mut().expect("loop scope was just pushed");
+            scope.insert(variable.to_string(), value);
+            match self.execute_block(body) {
+                Ok(ControlFlow::Break) => break,
+                Ok(ControlFlow::Next | ControlFlow::Continue) => {}
+                other => {
+                    result = other;
+                    break;
+                }
+            }
+        }
+        self.current_frame().pop();
+        result
+    }
+
+    // Runs `body` once with `variable` bound in a scope of its own, as for `rescue error`.
+    fn execute_with_binding(&mut self, variable: &str, value: Value, body: &[Statement]) -> Result<ControlFlow, RuntimeError> {
+        self.check_not_constant(variable, Some("the name of a rescued error"))?;
+        self.current_frame().push(HashMap::from([(variable.to_string(), value)]));
+        let result = self.execute_block(body);
+        self.current_frame().pop();
+        result
+    }
+
+    fn range_values(start: &Value, end: &Value, step: Option<&Value>) -> Result<impl Iterator<Item = Value>, RuntimeError> {
+        let number = |value: &Value, what: &str| match value {
+            Value::Number(n) => Ok(*n),
+            other => Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Type error: range {} must be a number, found {}", what, other.type_name()),
+            )),
+        };
+        let start = number(start, "start")?;
+        let end = number(end, "end")?;
+        // The step is a size; the direction always follows from start and end
+        let size = match step {
+            Some(step) => number(step, "step")?.abs(),
+            None => 1.0,
+        };
+        if size == 0.0 || size.is_nan() {
+            return Err(RuntimeError::new(ErrorKind::Value, "Range step cannot be zero"));
+        }
+        let step = if start <= end { size } else { -size };
+
+        Ok((0..)
+            .map(move |i| start + step * i as f64)
+            .take_while(move |n| if step > 0.0 { *n <= end } else { *n >= end })
+            .map(Value::Number))
+    }
+
+    fn assign(&mut self, target: &LetTarget, value: Value) -> Result<(), RuntimeError> {
+        match target {
+            LetTarget::Identifier(name) => self.set_variable(name, value),
+            LetTarget::Property(access) => match self.evaluate_expression(&access.object)? {
+                // Every importer shares a module's namespace, so nobody may change it
+                Value::Object(object) if self.loaded_modules.values().any(|module| Rc::ptr_eq(module, &object)) => {
+                    return Err(RuntimeError::new(
+                        ErrorKind::Constant,
+                        format!("Cannot set '{}' on an imported module; modules are read-only", access.property),
+                    ))
+                }
+                Value::Object(object) => object.borrow_mut().insert(access.property.clone(), value),
+                other => {
+                    return Err(RuntimeError::new(
+                        ErrorKind::Type,
+                        format!(
+                            "Type error: cannot set property '{}' on a {}",
+                            access.property,
+                            other.type_name()
+                        ),
+                    ))
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn read_property(object: &Value, property: &str) -> Result<Value, RuntimeError> {
+        let object = match object {
+            Value::Error(error) => return Self::read_error_property(error, property),
+            Value::Time(time) => {
+                return time.property(property).ok_or_else(|| {
+                    Self::missing_property("Time", property, "year, month, day, hour, minute, second, weekday")
+                })
+            }
+            Value::Duration(duration) => {
+                return duration
+                    .property(property)
+                    .ok_or_else(|| Self::missing_property("Duration", property, "days, hours, minutes, seconds"))
+            }
+            Value::Object(object) => object.borrow(),
+            other => {
+                return Err(RuntimeError::new(
+                    ErrorKind::Type,
+                    format!("Type error: cannot read property '{}' of a {}", property, other.type_name()),
+                ))
+            }
+        };
+        object.get(property).cloned().ok_or_else(|| {
+            let available: Vec<&str> = object.keys().map(|key| key.as_str()).collect();
+            RuntimeError::new(
+                ErrorKind::Name,
+                format!(
+                    "Object has no property '{}' (available: {})",
+                    property,
+                    if available.is_empty() { "none".to_string() } else { available.join(", ") }
+                ),
+            )
+        })
+    }
+
+    fn read_error_property(error: &RuntimeError, property: &str) -> Result<Value, RuntimeError> {
+        match property {
+            "kind" => Ok(Value::String(error.kind.name().to_string())),
+            "message" => Ok(Value::String(error.message.clone())),
+            "line" => Ok(error.line.map_or(Value::Nothing, |line| Value::Number(line as f64))),
+            "file" => Ok(error.file.clone().map_or(Value::Nothing, Value::String)),
+            _ => Err(Self::missing_property("Error", property, "kind, message, line, file")),
+        }
+    }
+
+    fn missing_property(owner: &str, property: &str, available: &str) -> RuntimeError {
+        RuntimeError::new(
+            ErrorKind::Name,
+            format!("{} has no property '{}' (available: {})", owner, property, available),
+        )
+    }
+
+    // Runs a module the first time it is imported and returns its namespace.
+    fn import_module(&mut self, name: &str) -> Result<Rc<RefCell<Object>>, RuntimeError> {
+        let import_error = |message: String| RuntimeError::new(ErrorKind::Import, message);
+        let base_dir = self.importing.last().map(|module| module.dir.clone()).unwrap_or_default();
+        let path = match modules::resolve(name, &base_dir) {
+            Some(ModuleSource::File(path)) => path,
+            Some(ModuleSource::Builtin(build)) => {
+                let key = format!("<builtin {}>", name);
+                let namespace = self.loaded_modules.entry(key).or_insert_with(|| Rc::new(RefCell::new(build())));
+                return Ok(Rc::clone(namespace));
+            }
+            None => return Err(import_error(format!("Module '{}' not found", name))),
+        };
+
+        let loading = LoadingModule::new(&path);
+        if let Some(namespace) = self.loaded_modules.get(&loading.key) {
+            return Ok(Rc::clone(namespace));
+        }
+        if let Some(start) = self.importing.iter().position(|module| module.key == loading.key) {
+            let cycle: Vec<&str> = self.importing[start..]
+                .iter()
+                .chain([&loading])
+                .map(|module| module.name.as_str())
+                .collect();
+            return Err(import_error(format!("Cyclic import: {}", cycle.join(" -> "))));
+        }
+
+        let key = loading.key.clone();
+        self.importing.push(loading);
+        let result = self.run_module(&path);
+        self.importing.pop();
+
+        let namespace = Rc::new(RefCell::new(result?));
+        self.loaded_modules.insert(key, Rc::clone(&namespace));
+        Ok(namespace)
+    }
+
+    // Lexes, parses, checks and runs a module file in a fresh top level. Every global
+    // it leaves behind becomes a property of its namespace.
+    fn run_module(&mut self, path: &Path) -> Result<Object, RuntimeError> {
+        let file = path.display();
+        let import_error = |message: String| RuntimeError::new(ErrorKind::Import, message);
+        let source = fs::read_to_string(path)
+            .map_err(|err| RuntimeError::new(ErrorKind::Io, format!("Cannot read module '{}': {}", file, err)))?;
+        let tokens = Lexer::new(&source)
+            .tokenize()
+            .map_err(|err| import_error(format!("Lexer error in '{}': {}", file, err)))?;
+        let program = Parser::new(tokens)
+            .parse()
+            .map_err(|err| import_error(format!("Parser error in '{}': {}", file, err)))?;
+
+        let mut checker = Checker::new();
+        checker.check(&program);
+        for warning in checker.warnings() {
+            eprintln!("Warning: {} in '{}'", warning, file);
+        }
+        if let Some(err) = checker.errors().first() {
+            return Err(import_error(format!("Check error in '{}': {}", file, err)));
+        }
+
+        let module = Rc::new(RefCell::new(Module {
+            file: Some(file.to_string()),
+            ..Module::default()
+        }));
+        let importer_module = mem::replace(&mut self.module, Rc::clone(&module));
+        let importer_frames = mem::replace(&mut self.frames, vec![Vec::new()]);
+        let result = self.execute_block(&program.statements);
+        self.module = importer_module;
+        self.frames = importer_frames;
+        result?;
+
+        let module = module.borrow();
+        let mut names: Vec<&String> = module.variables.keys().collect();
+        names.sort();
+        let mut namespace = Object::new();
+        for name in names {
+            namespace.insert(name.clone(), module.variables[name].clone());
+        }
+        Ok(namespace)
+    }
+
+    // Defaults are evaluated inside the new frame, so they can refer to earlier parameters.
+    fn bind_parameters(&mut self, function: &FunctionDef, arguments: Vec<Value>) -> Result<(), RuntimeError> {
+        let mut arguments = arguments.into_iter();
+        for parameter in &function.parameters {
+            self.check_not_constant(&parameter.name, Some("a parameter"))?;
+            let value = match (arguments.next(), &parameter.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.evaluate_expression(default)?,
+                (None, None) => unreachable!("arity is checked before binding"),
+            };
+            self.set_variable(&parameter.name, value);
+        }
         Ok(())
     }
-    
-    fn evaluate_expression(&mut self, expression: &Expression) -> Result<String, String> {
+
+    fn call_function(&mut self, function: &Function, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let function_module = Rc::clone(&function.module);
+        let function = &function.def;
+        let required = function.parameters.iter().filter(|p| p.default.is_none()).count();
+        Self::check_arity(&function.name, required, Some(function.parameters.len()), arguments.len())?;
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(
+                ErrorKind::Recursion,
+                format!("Maximum call depth exceeded in '{}'", function.name),
+            ));
+        }
+
+        self.frames.push(vec![HashMap::new()]);
+        let caller_module = mem::replace(&mut self.module, function_module);
+        let result = self.bind_parameters(function, arguments)
+            .and_then(|_| self.execute_block(&function.body));
+        self.module = caller_module;
+        self.frames.pop();
+
+        match result? {
+            ControlFlow::Return(value) => Ok(value),
+            // The parser keeps `break` and `continue` from escaping a function body
+            ControlFlow::Next | ControlFlow::Break | ControlFlow::Continue => Ok(Value::Nothing),
+        }
+    }
+
+    fn call_builtin(&mut self, builtin: &Builtin, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        Self::check_arity(builtin.name, builtin.min_args, builtin.max_args, arguments.len())?;
+        (builtin.run)(&mut self.host, arguments)
+    }
+
+    // `max` is `None` when any number of extra arguments is accepted.
+    fn check_arity(name: &str, min: usize, max: Option<usize>, given: usize) -> Result<(), RuntimeError> {
+        if given >= min && max.is_none_or(|max| given <= max) {
+            return Ok(());
+        }
+        let expected = match max {
+            Some(max) if max == min => min.to_string(),
+            Some(max) => format!("{} to {}", min, max),
+            None => format!("at least {}", min),
+        };
+        Err(RuntimeError::new(
+            ErrorKind::Argument,
+            format!("Function '{}' expects {} argument(s), got {}", name, expected, given),
+        ))
+    }
+
+    // A range arm only matches values it can be compared with: `when 1 to 5` never matches a string.
+    fn arm_matches(&mut self, pattern: &ChoosePattern, subject: &Value) -> Result<bool, RuntimeError> {
+        match pattern {
+            ChoosePattern::Value(expr) => Ok(self.evaluate_expression(expr)? == *subject),
+            ChoosePattern::Range(low, high) => {
+                let low = self.evaluate_expression(low)?;
+                let high = self.evaluate_expression(high)?;
+                Ok(matches!(subject.compare(&low), Ok(Ordering::Greater | Ordering::Equal))
+                    && matches!(subject.compare(&high), Ok(Ordering::Less | Ordering::Equal)))
+            }
+        }
+    }
+
+    fn evaluate_list(&mut self, expression: &Expression, action: &str) -> Result<Rc<RefCell<Vec<Value>>>, RuntimeError> {
+        match self.evaluate_expression(expression)? {
+            Value::List(items) => Ok(items),
+            other => Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Type error: cannot {} a {}", action, other.type_name()),
+            )),
+        }
+    }
+
+    fn evaluate_list_query(query: &ListQuery, value: Value) -> Result<Value, RuntimeError> {
+        let items = match value {
+            Value::List(items) => items.borrow().clone(),
+            Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
+            other => {
+                return Err(RuntimeError::new(
+                    ErrorKind::Type,
+                    format!("Type error: cannot take the {} of a {}", query.kind, other.type_name()),
+                ))
+            }
+        };
+        let item = match query.kind {
+            ListQueryKind::Length => return Ok(Value::Number(items.len() as f64)),
+            ListQueryKind::First => items.into_iter().next(),
+            ListQueryKind::Last => items.into_iter().last(),
+        };
+        item.ok_or_else(|| {
+            RuntimeError::new(ErrorKind::Value, format!("Cannot take the {} of an empty list", query.kind))
+        })
+    }
+
+    fn unpack(value: Value, count: usize) -> Result<Vec<Value>, RuntimeError> {
+        match value {
+            Value::List(items) => {
+                let items = items.borrow();
+                if items.len() != count {
+                    return Err(RuntimeError::new(
+                        ErrorKind::Value,
+                        format!("Cannot unpack {} value(s) into {} names", items.len(), count),
+                    ));
+                }
+                Ok(items.clone())
+            }
+            other => Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("Cannot unpack a {} into {} names", other.type_name(), count),
+            )),
+        }
+    }
+
+    // Conditions must evaluate to a boolean; anything else is a type error.
+    fn is_true(value: &Value) -> Result<bool, RuntimeError> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            other => Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Type error: condition must be a boolean, found {}", other.type_name()),
+            )),
+        }
+    }
+
+    fn evaluate_expression(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
         match expression {
-            Expression::Number(n) => Ok(n.to_string()),
-            Expression::String(s) => Ok(s.clone()),
+            Expression::Number(n) => Ok(Value::Number(*n)),
+            Expression::String(s) => Ok(Value::String(s.clone())),
+            Expression::Interpolation(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        InterpolationPart::Text(s) => text.push_str(s),
+                        InterpolationPart::Value { expression, format } => {
+                            let value = self.evaluate_expression(expression)?;
+                            match format {
+                                Some(spec) => text.push_str(&value.format(spec)?),
+                                None => text.push_str(&value.to_string()),
+                            }
+                        }
+                    }
+                }
+                Ok(Value::String(text))
+            }
+            Expression::Boolean(b) => Ok(Value::Boolean(*b)),
+            Expression::Nothing => Ok(Value::Nothing),
             Expression::Identifier(name) => {
-                if let Some(value) = self.variables.get(name) {
-                    Ok(value.clone())
-                } else {
-                    Ok(format!("<undefined: {}>", name))
+                match self.lookup_variable(name) {
+                    // A bare function name is a call without arguments
+                    Some(Value::Function(function)) => self.call_function(&function, Vec::new()),
+                    Some(Value::Builtin(builtin)) => self.call_builtin(&builtin, Vec::new()),
+                    Some(value) => Ok(value),
+                    None => Err(RuntimeError::new(ErrorKind::Name, format!("Undefined variable '{}'", name))),
+                }
+            }
+            Expression::List(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.evaluate_expression(item)?);
+                }
+                Ok(Value::list(values))
+            }
+            Expression::ListQuery(query) => {
+                let value = self.evaluate_expression(&query.list)?;
+                Self::evaluate_list_query(query, value)
+            }
+            Expression::Object(properties) => {
+                let mut object = Object::new();
+                for (key, expr) in properties {
+                    object.insert(key.clone(), self.evaluate_expression(expr)?);
+                }
+                Ok(Value::object(object))
+            }
+            Expression::Property(access) => {
+                let object = self.evaluate_expression(&access.object)?;
+                match Self::read_property(&object, &access.property)? {
+                    // Like a bare name, a function stored in a property is called without arguments
+                    Value::Function(function) => self.call_function(&function, Vec::new()),
+                    Value::Builtin(builtin) => self.call_builtin(&builtin, Vec::new()),
+                    value => Ok(value),
                 }
             }
             Expression::BinaryOp(binop) => {
-                let left_val = self.evaluate_expression(&binop.left)?;
-                let right_val = self.evaluate_expression(&binop.right)?;
-                
-                // Try to parse as numbers for comparison and arithmetic
-                if let (Ok(left_num), Ok(right_num)) = (left_val.parse::<f64>(), right_val.parse::<f64>()) {
-                    let result = match binop.operator {
-                        BinaryOperator::GreaterThan => return Ok((left_num > right_num).to_string()),
-                        BinaryOperator::LessThan => return Ok((left_num < right_num).to_string()),
-                        BinaryOperator::GreaterThanOrEqual => return Ok((left_num >= right_num).to_string()),
-                        BinaryOperator::LessThanOrEqual => return Ok((left_num <= right_num).to_string()),
-                        BinaryOperator::Equal => return Ok(((left_num - right_num).abs() < f64::EPSILON).to_string()),
-                        BinaryOperator::NotEqual => return Ok(((left_num - right_num).abs() >= f64::EPSILON).to_string()),
-                        BinaryOperator::Add => left_num + right_num,
-                        BinaryOperator::Subtract => left_num - right_num,
-                        BinaryOperator::Multiply => left_num * right_num,
-                        BinaryOperator::Divide => {
-                            if right_num == 0.0 {
-                                return Err("Division by zero".to_string());
-                            }
-                            left_num / right_num
-                        }
-                    };
-                    Ok(result.to_string())
-                } else {
-                    Ok(format!("({} {:?} {})", left_val, binop.operator, right_val))
+                let left = self.evaluate_expression(&binop.left)?;
+                let right = self.evaluate_expression(&binop.right)?;
+                Self::evaluate_binary(&binop.operator, left, right)
+            }
+            Expression::Logical(logical) => {
+                let left = self.evaluate_expression(&logical.left)?;
+                let left = Self::logical_operand(&left, &logical.operator)?;
+                // Short-circuit: the right side only runs when it can change the result
+                let decided = match logical.operator {
+                    LogicalOperator::And => !left,
+                    LogicalOperator::Or => left,
+                };
+                if decided {
+                    return Ok(Value::Boolean(left));
                 }
+                let right = self.evaluate_expression(&logical.right)?;
+                Ok(Value::Boolean(Self::logical_operand(&right, &logical.operator)?))
             }
+            Expression::Not(operand) => match self.evaluate_expression(operand)? {
+                Value::Boolean(b) => Ok(Value::Boolean(!b)),
+                other => Err(RuntimeError::new(
+                    ErrorKind::Type,
+                    format!("Type error: cannot apply 'not' to {}", other.type_name()),
+                )),
+            },
+            Expression::Negate(operand) => match self.evaluate_expression(operand)? {
+                Value::Number(n) => Ok(Value::Number(-n)),
+                other => Err(RuntimeError::new(
+                    ErrorKind::Type,
+                    format!("Type error: cannot negate a {}", other.type_name()),
+                )),
+            },
             Expression::FunctionCall(call) => {
-                Ok(format!("<function call: {}>", call.name))
+                // The callee is looked up without the automatic call a bare name gets
+                let (name, callee) = match &*call.callee {
+                    Expression::Identifier(name) => (name, self.lookup_variable(name)),
+                    Expression::Property(access) => {
+                        let object = self.evaluate_expression(&access.object)?;
+                        (&access.property, Some(Self::read_property(&object, &access.property)?))
+                    }
+                    _ => unreachable!("the parser only calls names and properties"),
+                };
+                let callee = match callee {
+                    Some(callee @ (Value::Function(_) | Value::Builtin(_))) => callee,
+                    Some(other) => {
+                        return Err(RuntimeError::new(
+                            ErrorKind::Type,
+                            format!("'{}' is a {}, not a function", name, other.type_name()),
+                        ))
+                    }
+                    None => {
+                        return Err(RuntimeError::new(
+                            ErrorKind::Name,
+                            format!("Undefined function '{}'", name),
+                        ))
+                    }
+                };
+                let mut arguments = Vec::new();
+                for argument in &call.arguments {
+                    arguments.push(self.evaluate_expression(argument)?);
+                }
+                match callee {
+                    Value::Builtin(builtin) => self.call_builtin(&builtin, arguments),
+                    Value::Function(function) => self.call_function(&function, arguments),
+                    _ => unreachable!("only functions get this far"),
+                }
+            }
+            Expression::Ask(ask) => self.ask(ask),
+        }
+    }
+
+    // Shows the prompt and reads one line. At the end of the input the answer is `nothing`.
+    fn ask(&mut self, ask: &Ask) -> Result<Value, RuntimeError> {
+        let io_error = |err: io::Error| RuntimeError::new(ErrorKind::Io, format!("Cannot read input: {}", err));
+        if let Some(prompt) = &ask.prompt {
+            let prompt = self.evaluate_expression(prompt)?;
+            print!("{}", prompt);
+            io::stdout().flush().map_err(io_error)?;
+        }
+
+        let mut line = String::new();
+        if self.input.read_line(&mut line).map_err(io_error)? == 0 {
+            return Ok(Value::Nothing);
+        }
+        let answer = line.strip_suffix('\n').unwrap_or(&line);
+        let answer = answer.strip_suffix('\r').unwrap_or(answer);
+
+        if !ask.numeric {
+            return Ok(Value::String(answer.to_string()));
+        }
+        match answer.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Value::Number(n)),
+            _ => Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("Expected a number, got \"{}\"", answer),
+            )),
+        }
+    }
+
+    fn logical_operand(value: &Value, operator: &LogicalOperator) -> Result<bool, RuntimeError> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            other => Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Type error: cannot apply '{}' to {}", operator, other.type_name()),
+            )),
+        }
+    }
+
+    fn evaluate_binary(operator: &BinaryOperator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+        match operator {
+            BinaryOperator::Equal => Ok(Value::Boolean(left == right)),
+            BinaryOperator::NotEqual => Ok(Value::Boolean(left != right)),
+            BinaryOperator::GreaterThan => Ok(Value::Boolean(left.compare(&right)? == Ordering::Greater)),
+            BinaryOperator::LessThan => Ok(Value::Boolean(left.compare(&right)? == Ordering::Less)),
+            BinaryOperator::GreaterThanOrEqual => Ok(Value::Boolean(left.compare(&right)? != Ordering::Less)),
+            BinaryOperator::LessThanOrEqual => Ok(Value::Boolean(left.compare(&right)? != Ordering::Greater)),
+            BinaryOperator::Contains | BinaryOperator::StartsWith | BinaryOperator::EndsWith => {
+                let found = match (&left, &right) {
+                    (Value::String(text), Value::String(part)) => match operator {
+                        BinaryOperator::Contains => text.contains(part.as_str()),
+                        BinaryOperator::StartsWith => text.starts_with(part.as_str()),
+                        _ => text.ends_with(part.as_str()),
+                    },
+                    // For lists: any item, the first item, or the last item
+                    (Value::List(items), item) => {
+                        let items = items.borrow();
+                        match operator {
+                            BinaryOperator::Contains => items.contains(item),
+                            BinaryOperator::StartsWith => items.first() == Some(item),
+                            _ => items.last() == Some(item),
+                        }
+                    }
+                    _ => return Err(Self::type_error(operator, &left, &right)),
+                };
+                Ok(Value::Boolean(found))
+            }
+            BinaryOperator::Add => match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+                // Adding anything to a string concatenates its printed form
+                (Value::String(_), _) | (_, Value::String(_)) => {
+                    Ok(Value::String(format!("{}{}", left, right)))
+                }
+                _ => Self::evaluate_time(operator, &left, &right),
+            },
+            BinaryOperator::Subtract
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo
+            | BinaryOperator::Power => {
+                let (a, b) = match (&left, &right) {
+                    (Value::Number(a), Value::Number(b)) => (*a, *b),
+                    _ => return Self::evaluate_time(operator, &left, &right),
+                };
+                match operator {
+                    BinaryOperator::Subtract => Ok(Value::Number(a - b)),
+                    BinaryOperator::Multiply => Ok(Value::Number(a * b)),
+                    BinaryOperator::Power => Ok(Value::Number(a.powf(b))),
+                    _ => {
+                        if b == 0.0 {
+                            return Err(RuntimeError::new(ErrorKind::DivisionByZero, "Division by zero"));
+                        }
+                        match operator {
+                            BinaryOperator::Modulo => Ok(Value::Number(a % b)),
+                            _ => Ok(Value::Number(a / b)),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // `+` and `-` move a time by a duration, and two times are a duration apart.
+    // Any other operands are a type error.
+    fn evaluate_time(operator: &BinaryOperator, left: &Value, right: &Value) -> Result<Value, RuntimeError> {
+        match (operator, left, right) {
+            (BinaryOperator::Add, Value::Time(time), Value::Duration(duration))
+            | (BinaryOperator::Add, Value::Duration(duration), Value::Time(time)) => Ok(Value::Time(time.add(*duration))),
+            (BinaryOperator::Add, Value::Duration(a), Value::Duration(b)) => {
+                Ok(Value::Duration(Duration(a.0.saturating_add(b.0))))
+            }
+            (BinaryOperator::Subtract, Value::Time(time), Value::Duration(duration)) => {
+                Ok(Value::Time(time.add(Duration(duration.0.saturating_neg()))))
             }
+            (BinaryOperator::Subtract, Value::Time(a), Value::Time(b)) => Ok(Value::Duration(a.since(*b))),
+            (BinaryOperator::Subtract, Value::Duration(a), Value::Duration(b)) => {
+                Ok(Value::Duration(Duration(a.0.saturating_sub(b.0))))
+            }
+            _ => Err(Self::type_error(operator, left, right)),
+        }
+    }
+
+    fn type_error(operator: &BinaryOperator, left: &Value, right: &Value) -> RuntimeError {
+        RuntimeError::new(
+            ErrorKind::Type,
+            format!(
+                "Type error: cannot apply '{}' to {} and {}",
+                operator,
+                left.type_name(),
+                right.type_name()
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::library::Random;
+    use crate::library::date_time;
+    use crate::parser::Parser;
+    use crate::testing::ScratchDir;
+    use std::io::Cursor;
+    use std::thread;
+
+    // Top-level variables after a run, as `repr` shows them. Values cannot leave the
+    // interpreter's thread, so tests look at their text.
+    type Globals = HashMap<String, String>;
+
+    // Runs a program the way `main` does, on a thread with the interpreter's stack, with
+    // `input` as everything typed in answer to `ask`.
+    fn run_with(host: impl FnOnce() -> Host + Send + 'static, source: &str, input: &str) -> Result<Globals, RuntimeError> {
+        let (source, input) = (source.to_string(), input.to_string());
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let tokens = Lexer::new(&source).tokenize().expect("lexer error");
+                let program = Parser::new(tokens).parse().expect("parser error");
+                let mut codegen = CodeGenerator::with_input(host(), Box::new(Cursor::new(input)));
+                codegen.interpret(&program, Path::new("test.de"))?;
+                let module = codegen.module.borrow();
+                Ok(module.variables.iter().map(|(name, value)| (name.clone(), value.repr())).collect())
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    fn run(source: &str) -> Result<Globals, RuntimeError> {
+        run_with(Host::new, source, "")
+    }
+
+    fn seeded(seed: u64) -> impl FnOnce() -> Host + Send + 'static {
+        move || {
+            let mut host = Host::new();
+            host.random = Random::new(seed);
+            host
+        }
+    }
+
+    const COUNT_DOWN: &str = "\
+define count_down with n
+    when n is 0 then
+        return 0
+    return 1 + count_down n - 1
+";
+
+    #[test]
+    fn recursion_reaches_the_call_depth_limit_without_overflowing() {
+        let globals = run(&format!("{}let depth be count_down 990\n", COUNT_DOWN)).unwrap();
+        assert_eq!(globals["depth"], "990");
+
+        let error = run(&format!("{}let depth be count_down 5000\n", COUNT_DOWN)).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Recursion);
+    }
+
+    #[test]
+    fn errors_in_a_module_name_its_file() {
+        let dir = ScratchDir::with_files("module-errors", &[("merr.de", "let a be 1\nlet b be 0\nshow a / b\n")]);
+        let file = dir.join("merr.de").display().to_string();
+
+        let error = run(&format!("let before be 1\nimport \"{}/merr\"\n", dir.display())).unwrap_err();
+        assert_eq!((error.line, error.file.as_deref()), (Some(3), Some(file.as_str())));
+        assert_eq!(error.to_string(), format!("Division by zero at line 3 in '{}'", file));
+
+        let source = format!(
+            "attempt\n    import \"{}/merr\"\nrescue error\n    let line be error.line\n    let file be error.file\n",
+            dir.display()
+        );
+        let globals = run(&source).unwrap();
+        assert_eq!(globals["line"], "3");
+        assert_eq!(globals["file"], Value::String(file).repr());
+
+        let error = run("let a be 1\nlet b be a / 0\n").unwrap_err();
+        assert_eq!((error.line, error.file), (Some(2), None));
+    }
+
+    #[test]
+    fn cyclic_imports_point_at_the_import_that_closes_the_cycle() {
+        let dir = ScratchDir::with_files("cyclic-imports", &[("ca.de", "import \"cb\"\n"), ("cb.de", "let x be 1\nimport \"ca\"\n")]);
+        let error = run(&format!("import \"{}/ca\"\n", dir.display())).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Import);
+        assert_eq!(error.message, "Cyclic import: ca.de -> cb.de -> ca.de");
+        assert_eq!((error.line, error.file), (Some(2), Some(dir.join("cb.de").display().to_string())));
+    }
+
+    #[test]
+    fn ask_reads_answers_from_the_input() {
+        let globals = run_with(Host::new, "let name be ask \"Name? \"\nlet age be ask number\n", "Ada\r\n 36 \n").unwrap();
+        assert_eq!(globals["name"], "\"Ada\"");
+        assert_eq!(globals["age"], "36");
+    }
+
+    #[test]
+    fn ask_number_rejects_an_answer_that_is_not_a_number() {
+        let error = run_with(Host::new, "let age be ask number\n", "thirty\n").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Value);
+        assert_eq!(error.message, "Expected a number, got \"thirty\"");
+        assert_eq!(error.line, Some(1));
+    }
+
+    #[test]
+    fn ask_number_can_be_retried_until_the_answer_is_a_number() {
+        let source = "\
+let tries be 0
+repeat while true
+    let tries be tries + 1
+    attempt
+        let age be ask number \"Enter your age: \"
+        break
+    rescue error
+        show \"Please enter a number\"
+";
+        let globals = run_with(Host::new, source, "thirty\n\n42\n").unwrap();
+        assert_eq!(globals["tries"], "3");
+        assert_eq!(globals["age"], "42");
+    }
+
+    #[test]
+    fn ask_gives_nothing_at_the_end_of_the_input() {
+        let globals = run_with(Host::new, "let name be ask\nlet city be ask\nlet age be ask number\n", "only\n").unwrap();
+        assert_eq!(globals["name"], "\"only\"");
+        assert_eq!(globals["city"], "nothing");
+        assert_eq!(globals["age"], "nothing");
+    }
+
+    const ROLLS: &str = "\
+import \"math\"
+let first_roll be math.random 1, 1000000
+let second_roll be math.random 1, 1000000
+let rolls be [first_roll, second_roll]
+";
+
+    #[test]
+    fn a_seed_repeats_the_same_random_numbers() {
+        // `--seed 7` on the command line
+        let first = run_with(seeded(7), ROLLS, "").unwrap();
+        let second = run_with(seeded(7), ROLLS, "").unwrap();
+        assert_eq!(first["rolls"], second["rolls"]);
+        assert_ne!(first["rolls"], run_with(seeded(8), ROLLS, "").unwrap()["rolls"]);
+
+        // `math.seed 7` in the program restarts the generator the same way
+        let reseeded = run(&format!("{}math.seed 7\n{}", ROLLS, ROLLS)).unwrap();
+        assert_eq!(reseeded["rolls"], first["rolls"]);
+    }
+
+    #[test]
+    fn a_fixed_clock_decides_what_now_reports() {
+        // `--now "2024-02-29 13:45:00"` on the command line
+        let host = || {
+            let mut host = Host::new();
+            host.clock = date_time::fixed_clock("2024-02-29 13:45:00").unwrap();
+            host
+        };
+        let source = "\
+import \"date_time\"
+let stamp be date_time.format_time date_time.now, \"YYYY-MM-DD HH:mm:ss\"
+let stands_still be date_time.now is date_time.now
+";
+        let globals = run_with(host, source, "").unwrap();
+        assert_eq!(globals["stamp"], "\"2024-02-29 13:45:00\"");
+        assert_eq!(globals["stands_still"], "true");
+    }
+
+    #[test]
+    fn imported_modules_are_read_only() {
+        let error = run("import \"math\"\nlet math.pi be 3\n").unwrap_err();
+        assert_eq!((error.kind, error.line), (ErrorKind::Constant, Some(2)));
+        let globals = run("import \"math\"\nattempt\n    let math.pi be 3\nrescue error\n    let kind be error.kind\nimport \"math\" as m2\nlet pi be m2.pi\n").unwrap();
+        assert_eq!(globals["kind"], "\"constant_error\"");
+        assert_eq!(globals["pi"], std::f64::consts::PI.to_string());
+
+        let dir = ScratchDir::with_files("read-only-modules", &[("counter.de", "const LIMIT be 10\n")]);
+        let error = run(&format!("import \"{}/counter\" as c\nlet c.LIMIT be 99\n", dir.display())).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Constant);
+    }
+
+    #[test]
+    fn constants_cannot_be_rebound_by_functions_defined_before_them() {
+        let cases = [
+            ("define f with n\n    let PI be n\n    return PI\n", "Cannot assign to constant 'PI' (declared at line 4)"),
+            ("define f with PI\n    return PI\n", "Cannot use constant 'PI' as a parameter (declared at line 3)"),
+            (
+                "define f with n\n    repeat for each PI in [n]\n        return PI\n",
+                "Cannot use constant 'PI' as a loop variable (declared at line 4)",
+            ),
+        ];
+        for (function, message) in cases {
+            let error = run(&format!("{}const PI be 3.14\nlet result be f 1\n", function)).unwrap_err();
+            assert_eq!((error.kind, error.message.as_str()), (ErrorKind::Constant, message));
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+
+// The broad category of a runtime error, readable from a program as `error.kind`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Type,
+    DivisionByZero,
+    Name,
+    Argument,
+    Value,
+    Constant,
+    Recursion,
+    Io,
+    Import,
+    Permission,
+    Network,
+    // Raised by the program itself with `fail with`
+    Failure,
+    // `system.exit_program` unwinds the program with this. It cannot be rescued.
+    Exit(i32),
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Type => "type_error",
+            ErrorKind::DivisionByZero => "division_by_zero",
+            ErrorKind::Name => "name_error",
+            ErrorKind::Argument => "argument_error",
+            ErrorKind::Value => "value_error",
+            ErrorKind::Constant => "constant_error",
+            ErrorKind::Recursion => "recursion_error",
+            ErrorKind::Io => "io_error",
+            ErrorKind::Import => "import_error",
+            ErrorKind::Permission => "permission_error",
+            ErrorKind::Network => "network_error",
+            ErrorKind::Failure => "failure",
+            ErrorKind::Exit(_) => "exit",
+        }
+    }
+}
+
+// An error raised while the program runs. It can be caught with `attempt ... rescue`,
+// and otherwise stops the program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    // The line of the innermost statement that was running, filled in as the error
+    // leaves that statement
+    pub line: Option<usize>,
+    // The module that line is in, when it is not the program file
+    pub file: Option<String>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        RuntimeError {
+            kind,
+            message: message.into(),
+            line: None,
+            file: None,
+        }
+    }
+
+    pub fn at_line(mut self, line: usize, file: Option<&str>) -> Self {
+        if self.line.is_none() {
+            self.line = Some(line);
+            self.file = file.map(str::to_string);
+        }
+        self
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, &self.file) {
+            (Some(line), Some(file)) => write!(f, "{} at line {} in '{}'", self.message, line, file),
+            (Some(line), None) => write!(f, "{} at line {}", self.message, line),
+            (None, _) => write!(f, "{}", self.message),
+        }
+    }
+}
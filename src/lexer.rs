@@ -2,6 +2,7 @@
 pub enum Token {
     // Keywords
     Let,
+    Const,
     Be,
     When,
     Then,
@@ -10,6 +11,31 @@ pub enum Token {
     Define,
     With,
     End,
+    Return,
+    Repeat,
+    While,
+    Break,
+    Continue,
+    For,
+    Each,
+    From,
+    To,
+    By,
+    In,
+    Add,
+    Remove,
+    Length,
+    First,
+    Last,
+    Of,
+    Choose,
+    Attempt,
+    Rescue,
+    Always,
+    Fail,
+    Ask,
+    Import,
+    As,
     
     // Comparators
     IsGreaterThan,
@@ -18,10 +44,22 @@ pub enum Token {
     IsLessThanOrEqual,
     IsEqual,
     IsNotEqual,
+    Contains,
+    StartsWith,
+    EndsWith,
+
+    // Logical operators
+    And,
+    Or,
+    Not,
     
     // Literals
+    True,
+    False,
+    Nothing,
     Number(f64),
     String(String),
+    InterpolatedString(Vec<StringPart>),
     Identifier(String),
 
     // Operators
@@ -29,6 +67,19 @@ pub enum Token {
     Minus,
     Multiply,
     Divide,
+    Modulo,
+    Power,
+
+    // Punctuation
+    Comma,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Dot,
     
     // Whitespace and structure
     Newline,
@@ -39,6 +90,135 @@ pub enum Token {
     Eof,
 }
 
+// A piece of a string containing `{...}`. Embedded expressions are kept as source
+// text, with the position of their first character, for the parser to read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Expression {
+        source: String,
+        format: Option<String>,
+        line: usize,
+        column: usize,
+    },
+}
+
+// A token together with the line and column where it starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub line: usize,
+    pub column: usize,
+}
+
+// Words that are always keywords, apart from a property name after a dot.
+const KEYWORDS: &[(&str, Token)] = &[
+    ("let", Token::Let),
+    ("const", Token::Const),
+    ("be", Token::Be),
+    ("when", Token::When),
+    ("then", Token::Then),
+    ("otherwise", Token::Otherwise),
+    ("show", Token::Show),
+    ("define", Token::Define),
+    ("with", Token::With),
+    ("end", Token::End),
+    ("return", Token::Return),
+    ("repeat", Token::Repeat),
+    ("while", Token::While),
+    ("break", Token::Break),
+    ("continue", Token::Continue),
+    ("for", Token::For),
+    ("each", Token::Each),
+    ("from", Token::From),
+    ("to", Token::To),
+    ("in", Token::In),
+    ("add", Token::Add),
+    ("remove", Token::Remove),
+    ("length", Token::Length),
+    ("first", Token::First),
+    ("last", Token::Last),
+    ("of", Token::Of),
+    ("choose", Token::Choose),
+    ("attempt", Token::Attempt),
+    ("rescue", Token::Rescue),
+    ("always", Token::Always),
+    ("fail", Token::Fail),
+    ("ask", Token::Ask),
+    ("import", Token::Import),
+    ("as", Token::As),
+    ("true", Token::True),
+    ("false", Token::False),
+    ("nothing", Token::Nothing),
+    ("and", Token::And),
+    ("or", Token::Or),
+    ("not", Token::Not),
+];
+
+// The word a keyword token was read from, so a keyword can still be named where only
+// a name can appear: `import length as text_length from "string_utils"`.
+pub fn keyword_word(token: &Token) -> Option<&'static str> {
+    KEYWORDS.iter().find(|(_, keyword)| keyword == token).map(|(word, _)| *word)
+}
+
+// Operator phrases and their tokens. Several phrases may share a token; the
+// lexer always picks the longest phrase that matches. They only come right after a
+// value, so anywhere else these words are ordinary names: `let times be 3`.
+const OPERATOR_PHRASES: &[(&str, Token)] = &[
+    ("is greater than or equal to", Token::IsGreaterThanOrEqual),
+    ("is greater than or equal", Token::IsGreaterThanOrEqual),
+    ("is at least", Token::IsGreaterThanOrEqual),
+    ("is less than or equal to", Token::IsLessThanOrEqual),
+    ("is less than or equal", Token::IsLessThanOrEqual),
+    ("is at most", Token::IsLessThanOrEqual),
+    ("is greater than", Token::IsGreaterThan),
+    ("is less than", Token::IsLessThan),
+    ("is not equal to", Token::IsNotEqual),
+    ("is not equal", Token::IsNotEqual),
+    ("is not", Token::IsNotEqual),
+    ("is equal to", Token::IsEqual),
+    ("is equal", Token::IsEqual),
+    ("is", Token::IsEqual),
+    ("contains", Token::Contains),
+    ("starts with", Token::StartsWith),
+    ("ends with", Token::EndsWith),
+    ("plus", Token::Plus),
+    ("minus", Token::Minus),
+    ("times", Token::Multiply),
+    ("divided by", Token::Divide),
+    ("modulo", Token::Modulo),
+    // The step of a counting loop, `from 1 to 10 by 2`
+    ("by", Token::By),
+];
+
+// Whether a token can be the last one of a value, so an operator may follow it.
+fn ends_value(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_)
+            | Token::Number(_)
+            | Token::String(_)
+            | Token::InterpolatedString(_)
+            | Token::True
+            | Token::False
+            | Token::Nothing
+            // A bare `ask`; `ask number` already ends in a name
+            | Token::Ask
+            | Token::RightParen
+            | Token::RightBracket
+            | Token::RightBrace
+    )
+}
+
+// Where the lexer is in the input, so a failed lookahead can rewind.
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    position: usize,
+    current_char: Option<char>,
+    line: usize,
+    column: usize,
+}
+
 pub struct Lexer<'a> {
     input: &'a str,
     position: usize,
@@ -46,6 +226,8 @@ pub struct Lexer<'a> {
     line: usize,
     column: usize,
     indent_stack: Vec<usize>,
+    // Open `(`, `[` and `{` pairs; line breaks inside them do not end the statement
+    bracket_depth: usize,
 }
 
 impl<'a> Lexer<'a> {
@@ -57,23 +239,32 @@ impl<'a> Lexer<'a> {
             line: 1,
             column: 0,
             indent_stack: vec![0],
+            bracket_depth: 0,
         };
         lexer.current_char = lexer.input.chars().next();
         lexer
     }
 
+    // For source embedded in a larger file, so positions match the original.
+    pub fn starting_at(mut self, line: usize, column: usize) -> Self {
+        self.line = line;
+        self.column = column;
+        self
+    }
+
     fn advance(&mut self) {
         if self.current_char == Some('\n') {
-            self.line = self.line + 1;
+            self.line += 1;
             self.column = 0;
         } else {
-            self.column = self.column + 1;
+            self.column += 1;
         }
 
-        self.position = self.position + 1;
+        self.position += 1;
         self.current_char = self.input.chars().nth(self.position);
     }   
 
+    #[allow(dead_code)]
     fn peek(&self) -> Option<char> {
         self.input.chars().nth(self.position + 1)
     }
@@ -103,14 +294,21 @@ impl<'a> Lexer<'a> {
         number.parse().unwrap_or(0.0)
     }
 
-    fn read_string(&mut self) -> Result<String, String> {
+    fn read_string(&mut self) -> Result<Token, String> {
         let mut string = String::new();
+        let mut parts = Vec::new();
         self.advance(); // Go over the Starting Quote
         
         while let Some(ch) = self.current_char {
             if ch == '"' {
                 self.advance(); // Go Over the Closing Quote
-                return Ok(string);
+                if parts.is_empty() {
+                    return Ok(Token::String(string));
+                }
+                if !string.is_empty() {
+                    parts.push(StringPart::Text(string));
+                }
+                return Ok(Token::InterpolatedString(parts));
             }
             if ch == '\\' {
                 self.advance();
@@ -120,8 +318,22 @@ impl<'a> Lexer<'a> {
                     Some('r') => string.push('\r'),
                     Some('\\') => string.push('\\'),
                     Some('"') => string.push('"'),
+                    Some('{') => string.push('{'),
+                    Some('}') => string.push('}'),
                     _ => return Err("Invalid escape sequence".to_string()),
                 }
+            } else if ch == '{' {
+                self.advance(); // Go Over the Opening Brace
+                if !string.is_empty() {
+                    parts.push(StringPart::Text(std::mem::take(&mut string)));
+                }
+                parts.push(self.read_interpolation()?);
+                continue;
+            } else if ch == '}' {
+                return Err(format!(
+                    "Unmatched '}}' in string at line {}, column {} (write \\}} for a literal brace)",
+                    self.line, self.column
+                ));
             } else {
                 string.push(ch);
             }
@@ -131,6 +343,62 @@ impl<'a> Lexer<'a> {
         Err("Unterminated string".to_string())
     }
 
+    // Reads `expression` or `expression:format` up to the closing brace. Brackets
+    // and strings inside the expression are skipped over, so `{to_upper "}"}` works.
+    fn read_interpolation(&mut self) -> Result<StringPart, String> {
+        let (line, column) = (self.line, self.column);
+        let mut source = String::new();
+        let mut format = None;
+        let mut depth = 0;
+        let mut in_string = false;
+
+        loop {
+            let ch = match self.current_char {
+                Some('\n') | None => {
+                    return Err(format!("Unterminated '{{' in string at line {}, column {}", line, column));
+                }
+                Some(ch) => ch,
+            };
+            self.advance();
+
+            if in_string {
+                if ch == '\\' {
+                    if let Some(escaped) = self.current_char {
+                        source.push(ch);
+                        source.push(escaped);
+                        self.advance();
+                        continue;
+                    }
+                } else if ch == '"' {
+                    in_string = false;
+                }
+            } else {
+                match ch {
+                    '"' => in_string = true,
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' => depth -= 1,
+                    '}' if depth == 0 => break,
+                    '}' => depth -= 1,
+                    ':' if depth == 0 && format.is_none() => {
+                        format = Some(String::new());
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            match format.as_mut() {
+                Some(format) => format.push(ch),
+                None => source.push(ch),
+            }
+        }
+
+        if source.trim().is_empty() {
+            return Err(format!("Empty '{{}}' in string at line {}, column {}", line, column));
+        }
+        Ok(StringPart::Expression { source, format, line, column })
+    }
+
     fn read_identifier(&mut self) -> String {
         let mut identifier = String::new();
 
@@ -171,27 +439,36 @@ impl<'a> Lexer<'a> {
     }
     */
 
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.position,
+            current_char: self.current_char,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.position = checkpoint.position;
+        self.current_char = checkpoint.current_char;
+        self.line = checkpoint.line;
+        self.column = checkpoint.column;
+    }
+
     fn try_match_keyword(&mut self, keyword: &str) -> bool {
-        let saved_position = self.position;
-        let saved_char = self.current_char;
-        let saved_line = self.line;
-        let saved_column = self.column;
+        let saved = self.checkpoint();
 
         let words: Vec<&str> = keyword.split(' ').collect();
 
         for (i, word) in words.iter().enumerate() {
-            if (i > 0) {
+            if i > 0 {
                 self.skip_whitespace();
             }
             
             let read_word = self.read_identifier();
 
             if read_word != *word {
-                self.position = saved_position;
-                self.current_char = saved_char;
-                self.line = saved_line;
-                self.column = saved_column;
-
+                self.rewind(saved);
                 return false;
             }
         }
@@ -199,36 +476,36 @@ impl<'a> Lexer<'a> {
         true
     }
 
-    fn keyword_or_identifier(&mut self) -> Token {
-        // Try to match multi-word keywords first (longest first)
-        let keywords = [
-            ("is greater than or equal", Token::IsGreaterThanOrEqual),
-            ("is less than or equal", Token::IsLessThanOrEqual),
-            ("is greater than", Token::IsGreaterThan),
-            ("is less than", Token::IsLessThan),
-            ("is not equal", Token::IsNotEqual),
-            ("is equal", Token::IsEqual),
-        ];
-        
-        for (keyword, token) in keywords.iter() {
-            if self.try_match_keyword(keyword) {
-                return token.clone();
+    // `after_value` says whether the previous token ends a value, the only place an
+    // operator phrase can start.
+    fn keyword_or_identifier(&mut self, after_value: bool) -> Token {
+        // Every phrase that can match here is tried and the one covering the most
+        // words wins, so `is not equal to` beats `is not`, which beats `is`. Words
+        // are compared whole, so `island` or `divided_total` stay identifiers.
+        let start = self.checkpoint();
+        let mut longest: Option<(Checkpoint, Token)> = None;
+
+        let phrases = if after_value { OPERATOR_PHRASES } else { &[] };
+        for (phrase, token) in phrases {
+            if self.try_match_keyword(phrase) {
+                let end = self.checkpoint();
+                if longest.as_ref().is_none_or(|(best, _)| end.position > best.position) {
+                    longest = Some((end, token.clone()));
+                }
+                self.rewind(start);
             }
         }
+
+        if let Some((end, token)) = longest {
+            self.rewind(end);
+            return token;
+        }
         
         // Try single-word keywords
         let word = self.read_identifier();
-        match word.as_str() {
-            "let" => Token::Let,
-            "be" => Token::Be,
-            "when" => Token::When,
-            "then" => Token::Then,
-            "otherwise" => Token::Otherwise,
-            "show" => Token::Show,
-            "define" => Token::Define,
-            "with" => Token::With,
-            "end" => Token::End,
-            _ => Token::Identifier(word),
+        match KEYWORDS.iter().find(|(keyword, _)| *keyword == word) {
+            Some((_, token)) => token.clone(),
+            None => Token::Identifier(word),
         }
     }
 
@@ -270,26 +547,35 @@ impl<'a> Lexer<'a> {
         tokens
     }
     
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
+    pub fn tokenize(&mut self) -> Result<Vec<SpannedToken>, String> {
         let mut tokens = Vec::new();
+        let mut spanned: Vec<SpannedToken> = Vec::new();
         
         while let Some(ch) = self.current_char {
+            let (line, column) = (self.line, self.column);
             match ch {
                 ' ' | '\t' => self.skip_whitespace(),
+                '\n' if self.bracket_depth > 0 => self.advance(),
                 '\n' => {
                     let newline_tokens = self.handle_newline_and_indentation();
                     tokens.extend(newline_tokens);
                 }
                 '"' => {
                     let string = self.read_string()?;
-                    tokens.push(Token::String(string));
+                    tokens.push(string);
                 }
                 '0'..='9' => {
                     let number = self.read_number();
                     tokens.push(Token::Number(number));
                 }
                 'a'..='z' | 'A'..='Z' | '_' => {
-                    let token = self.keyword_or_identifier();
+                    // A property name is never a keyword: `string_utils.length`
+                    let previous = spanned.last().map(|t| &t.token);
+                    let token = if previous == Some(&Token::Dot) {
+                        Token::Identifier(self.read_identifier())
+                    } else {
+                        self.keyword_or_identifier(previous.is_some_and(ends_value))
+                    };
                     tokens.push(token);
                 }
                 '+' => {
@@ -308,11 +594,51 @@ impl<'a> Lexer<'a> {
                     tokens.push(Token::Divide);
                     self.advance();
                 }
+                ',' => {
+                    tokens.push(Token::Comma);
+                    self.advance();
+                }
+                '%' => {
+                    tokens.push(Token::Modulo);
+                    self.advance();
+                }
+                '^' => {
+                    tokens.push(Token::Power);
+                    self.advance();
+                }
+                '(' | '[' | '{' => {
+                    tokens.push(match ch {
+                        '(' => Token::LeftParen,
+                        '[' => Token::LeftBracket,
+                        _ => Token::LeftBrace,
+                    });
+                    self.bracket_depth += 1;
+                    self.advance();
+                }
+                ')' | ']' | '}' => {
+                    tokens.push(match ch {
+                        ')' => Token::RightParen,
+                        ']' => Token::RightBracket,
+                        _ => Token::RightBrace,
+                    });
+                    self.bracket_depth = self.bracket_depth.saturating_sub(1);
+                    self.advance();
+                }
+                ':' => {
+                    tokens.push(Token::Colon);
+                    self.advance();
+                }
+                '.' => {
+                    tokens.push(Token::Dot);
+                    self.advance();
+                }
                 _ => {
                     return Err(format!("Unexpected character: '{}' at line {}, column {}", 
                                      ch, self.line, self.column));
                 }
             }
+            // Every token produced by this step starts where the step started
+            spanned.extend(tokens.drain(..).map(|token| SpannedToken { token, line, column }));
         }
         
         // Add final dedents for any remaining indentation
@@ -322,6 +648,64 @@ impl<'a> Lexer<'a> {
         }
         
         tokens.push(Token::Eof);
-        Ok(tokens)
+        let (line, column) = (self.line, self.column);
+        spanned.extend(tokens.drain(..).map(|token| SpannedToken { token, line, column }));
+        Ok(spanned)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        let mut tokens: Vec<Token> = Lexer::new(source).tokenize().unwrap().into_iter().map(|t| t.token).collect();
+        assert_eq!(tokens.pop(), Some(Token::Eof));
+        tokens
+    }
+
+    fn name(name: &str) -> Token {
+        Token::Identifier(name.to_string())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn operator_words_are_names_outside_operator_position() {
+        for word in ["times", "plus", "minus", "modulo", "contains", "by", "is"] {
+            assert_eq!(tokens(&format!("let {} be 3", word)), [Token::Let, name(word), Token::Be, Token::Number(3.0)]);
+        }
+    }
+
+    #[test]
+    fn operator_words_after_a_value_are_operators() {
+        assert_eq!(tokens("times times times"), [name("times"), Token::Multiply, name("times")]);
+        assert_eq!(tokens("(a) plus [b]"), [
+            Token::LeftParen,
+            name("a"),
+            Token::RightParen,
+            Token::Plus,
+            Token::LeftBracket,
+            name("b"),
+            Token::RightBracket,
+        ]);
+        assert_eq!(tokens("\"abc\" contains contains"), [Token::String("abc".to_string()), Token::Contains, name("contains")]);
+        assert_eq!(tokens("to 9 by step"), [Token::To, Token::Number(9.0), Token::By, name("step")]);
+    }
+
+    #[test]
+    fn the_longest_operator_phrase_wins() {
+        assert_eq!(tokens("a is not equal to b"), [name("a"), Token::IsNotEqual, name("b")]);
+        assert_eq!(tokens("a is at least b"), [name("a"), Token::IsGreaterThanOrEqual, name("b")]);
+        assert_eq!(tokens("a is island"), [name("a"), Token::IsEqual, name("island")]);
+    }
+
+    #[test]
+    fn a_bare_ask_is_a_value() {
+        assert_eq!(tokens("when ask is \"y\" then"), [
+            Token::When,
+            Token::Ask,
+            Token::IsEqual,
+            Token::String("y".to_string()),
+            Token::Then,
+        ]);
+        assert_eq!(tokens("ask number plus 1"), [Token::Ask, name("number"), Token::Plus, Token::Number(1.0)]);
+    }
+}
@@ -0,0 +1,486 @@
+use super::{define, number, string, type_error};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::value::{Object, Value};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MILLIS_PER_SECOND: i64 = 1000;
+const MILLIS_PER_MINUTE: i64 = 60 * MILLIS_PER_SECOND;
+const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+// Longest first, so `MMMM` is not read as `MM` twice.
+const FORMAT_TOKENS: [&str; 11] = ["YYYY", "MMMM", "dddd", "MMM", "ddd", "YY", "MM", "DD", "HH", "mm", "ss"];
+
+// What `now` reports. A fixed clock makes output that depends on the time repeatable.
+pub enum Clock {
+    System,
+    Fixed(DateTime),
+}
+
+impl Clock {
+    pub fn now(&self) -> DateTime {
+        match self {
+            Clock::System => {
+                let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                DateTime(elapsed.as_millis() as i64)
+            }
+            Clock::Fixed(time) => *time,
+        }
+    }
+}
+
+// A moment in time, in milliseconds since 1970-01-01 00:00:00 UTC. All dates are UTC.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct DateTime(pub i64);
+
+// The span between two moments, in milliseconds. Negative when it runs backwards.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Duration(pub i64);
+
+// A date and time split into calendar fields. Months and days start at 1.
+struct Parts {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    millis: i64,
+}
+
+impl DateTime {
+    fn from_parts(parts: &Parts) -> Option<DateTime> {
+        let valid = (1..=9999).contains(&parts.year)
+            && (1..=12).contains(&parts.month)
+            && (1..=days_in_month(parts.year, parts.month)).contains(&parts.day)
+            && (0..24).contains(&parts.hour)
+            && (0..60).contains(&parts.minute)
+            && (0..60).contains(&parts.second);
+        if !valid {
+            return None;
+        }
+        let days = days_from_civil(parts.year, parts.month, parts.day);
+        Some(DateTime(
+            days * MILLIS_PER_DAY
+                + parts.hour * MILLIS_PER_HOUR
+                + parts.minute * MILLIS_PER_MINUTE
+                + parts.second * MILLIS_PER_SECOND
+                + parts.millis,
+        ))
+    }
+
+    fn parts(&self) -> Parts {
+        let days = self.0.div_euclid(MILLIS_PER_DAY);
+        let rest = self.0.rem_euclid(MILLIS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        Parts {
+            year,
+            month,
+            day,
+            hour: rest / MILLIS_PER_HOUR,
+            minute: rest % MILLIS_PER_HOUR / MILLIS_PER_MINUTE,
+            second: rest % MILLIS_PER_MINUTE / MILLIS_PER_SECOND,
+            millis: rest % MILLIS_PER_SECOND,
+        }
+    }
+
+    // 1970-01-01 was a Thursday.
+    fn weekday(&self) -> &'static str {
+        WEEKDAYS[(self.0.div_euclid(MILLIS_PER_DAY) + 3).rem_euclid(7) as usize]
+    }
+
+    pub fn add(&self, duration: Duration) -> DateTime {
+        DateTime(self.0.saturating_add(duration.0))
+    }
+
+    pub fn since(&self, earlier: DateTime) -> Duration {
+        Duration(self.0.saturating_sub(earlier.0))
+    }
+
+    // `time.year`, `time.weekday` and the other calendar fields.
+    pub fn property(&self, name: &str) -> Option<Value> {
+        let parts = self.parts();
+        let field = match name {
+            "year" => parts.year,
+            "month" => parts.month,
+            "day" => parts.day,
+            "hour" => parts.hour,
+            "minute" => parts.minute,
+            "second" => parts.second,
+            "weekday" => return Some(Value::String(self.weekday().to_string())),
+            _ => return None,
+        };
+        Some(Value::Number(field as f64))
+    }
+
+    pub fn format(&self, format: &str) -> String {
+        let parts = self.parts();
+        let mut text = String::new();
+        for piece in pieces(format) {
+            let token = match piece {
+                Piece::Text(literal) => {
+                    text.push_str(literal);
+                    continue;
+                }
+                Piece::Token(token) => token,
+            };
+            let month = MONTHS[parts.month as usize - 1];
+            let weekday = self.weekday();
+            let piece = match token {
+                "YYYY" => format!("{:04}", parts.year),
+                "YY" => format!("{:02}", parts.year.rem_euclid(100)),
+                "MMMM" => month.to_string(),
+                "MMM" => month[..3].to_string(),
+                "MM" => format!("{:02}", parts.month),
+                "DD" => format!("{:02}", parts.day),
+                "dddd" => weekday.to_string(),
+                "ddd" => weekday[..3].to_string(),
+                "HH" => format!("{:02}", parts.hour),
+                "mm" => format!("{:02}", parts.minute),
+                _ => format!("{:02}", parts.second),
+            };
+            text.push_str(&piece);
+        }
+        text
+    }
+
+    // Reads text written in `format`. Fields the format leaves out default to the
+    // start of the day, or to January 1st.
+    pub fn parse(text: &str, format: &str) -> Option<DateTime> {
+        let mut parts = Parts { year: 1970, month: 1, day: 1, hour: 0, minute: 0, second: 0, millis: 0 };
+        let mut text = text;
+        for piece in pieces(format) {
+            let token = match piece {
+                Piece::Text(literal) => {
+                    text = text.strip_prefix(literal)?;
+                    continue;
+                }
+                Piece::Token(token) => token,
+            };
+            match token {
+                "YYYY" => parts.year = read_digits(&mut text, 4)?,
+                "YY" => parts.year = 2000 + read_digits(&mut text, 2)?,
+                "MMMM" => parts.month = read_name(&mut text, &MONTHS, None)? as i64 + 1,
+                "MMM" => parts.month = read_name(&mut text, &MONTHS, Some(3))? as i64 + 1,
+                "MM" => parts.month = read_digits(&mut text, 2)?,
+                "DD" => parts.day = read_digits(&mut text, 2)?,
+                // The weekday follows from the date, so it is only checked for spelling
+                "dddd" => {
+                    read_name(&mut text, &WEEKDAYS, None)?;
+                }
+                "ddd" => {
+                    read_name(&mut text, &WEEKDAYS, Some(3))?;
+                }
+                "HH" => parts.hour = read_digits(&mut text, 2)?,
+                "mm" => parts.minute = read_digits(&mut text, 2)?,
+                _ => parts.second = read_digits(&mut text, 2)?,
+            }
+        }
+        if !text.is_empty() {
+            return None;
+        }
+        DateTime::from_parts(&parts)
+    }
+}
+
+impl Duration {
+    // `duration.days`, `duration.hours` and so on give the whole span in that unit.
+    pub fn property(&self, name: &str) -> Option<Value> {
+        let unit = match name {
+            "days" => MILLIS_PER_DAY,
+            "hours" => MILLIS_PER_HOUR,
+            "minutes" => MILLIS_PER_MINUTE,
+            "seconds" => MILLIS_PER_SECOND,
+            _ => return None,
+        };
+        Some(Value::Number(self.0 as f64 / unit as f64))
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format("YYYY-MM-DD HH:mm:ss"))
+    }
+}
+
+// `2 days 3 hours`, `1 minute 30 seconds`, `-1 day`
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 < 0 {
+            write!(f, "-")?;
+        }
+        let mut rest = self.0.unsigned_abs() as i64;
+        let mut pieces = Vec::new();
+        for (unit, name) in [(MILLIS_PER_DAY, "day"), (MILLIS_PER_HOUR, "hour"), (MILLIS_PER_MINUTE, "minute")] {
+            let count = rest / unit;
+            rest %= unit;
+            if count > 0 {
+                pieces.push(format!("{} {}{}", count, name, if count == 1 { "" } else { "s" }));
+            }
+        }
+        if rest > 0 || pieces.is_empty() {
+            let seconds = rest as f64 / MILLIS_PER_SECOND as f64;
+            pieces.push(format!("{} second{}", seconds, if seconds == 1.0 { "" } else { "s" }));
+        }
+        write!(f, "{}", pieces.join(" "))
+    }
+}
+
+pub fn module() -> Object {
+    let mut date_time = Object::new();
+
+    define(&mut date_time, "now", 0, Some(0), |host, _| Ok(Value::Time(host.clock.now())));
+    // `make_time 2024, 3, 15` or `make_time 2024, 3, 15, 9, 30, 0`
+    define(&mut date_time, "make_time", 3, Some(6), |_, args| {
+        let mut fields = [1970, 1, 1, 0, 0, 0];
+        for (field, arg) in fields.iter_mut().zip(&args) {
+            *field = whole("make_time", arg)?;
+        }
+        let [year, month, day, hour, minute, second] = fields;
+        let parts = Parts { year, month, day, hour, minute, second, millis: 0 };
+        DateTime::from_parts(&parts).map(Value::Time).ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::Value,
+                format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} is not a valid date and time", year, month, day, hour, minute, second),
+            )
+        })
+    });
+    define(&mut date_time, "format_time", 2, Some(2), |_, args| {
+        let time = time("format_time", &args[0])?;
+        Ok(Value::String(time.format(string("format_time", &args[1])?)))
+    });
+    define(&mut date_time, "parse_time", 2, Some(2), |_, args| {
+        let text = string("parse_time", &args[0])?;
+        let format = string("parse_time", &args[1])?;
+        DateTime::parse(text, format).map(Value::Time).ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::Value,
+                format!("Cannot read \"{}\" as a time in the format \"{}\"", text, format),
+            )
+        })
+    });
+    define(&mut date_time, "add_days", 2, Some(2), |_, args| {
+        let days = span("add_days", &args[1], MILLIS_PER_DAY)?;
+        Ok(Value::Time(time("add_days", &args[0])?.add(days)))
+    });
+    define(&mut date_time, "subtract_days", 2, Some(2), |_, args| {
+        let days = span("subtract_days", &args[1], -MILLIS_PER_DAY)?;
+        Ok(Value::Time(time("subtract_days", &args[0])?.add(days)))
+    });
+    // How long after `earlier` the first time is; negative if it comes before
+    define(&mut date_time, "difference", 2, Some(2), |_, args| {
+        let later = time("difference", &args[0])?;
+        Ok(Value::Duration(later.since(time("difference", &args[1])?)))
+    });
+    define(&mut date_time, "weekday", 1, Some(1), |_, args| {
+        Ok(Value::String(time("weekday", &args[0])?.weekday().to_string()))
+    });
+
+    define(&mut date_time, "days", 1, Some(1), |_, args| Ok(Value::Duration(span("days", &args[0], MILLIS_PER_DAY)?)));
+    define(&mut date_time, "hours", 1, Some(1), |_, args| {
+        Ok(Value::Duration(span("hours", &args[0], MILLIS_PER_HOUR)?))
+    });
+    define(&mut date_time, "minutes", 1, Some(1), |_, args| {
+        Ok(Value::Duration(span("minutes", &args[0], MILLIS_PER_MINUTE)?))
+    });
+    define(&mut date_time, "seconds", 1, Some(1), |_, args| {
+        Ok(Value::Duration(span("seconds", &args[0], MILLIS_PER_SECOND)?))
+    });
+
+    date_time
+}
+
+// The clock used when the command line fixes the time with `--now`.
+pub fn fixed_clock(text: &str) -> Option<Clock> {
+    DateTime::parse(text, "YYYY-MM-DD HH:mm:ss")
+        .or_else(|| DateTime::parse(text, "YYYY-MM-DD"))
+        .map(Clock::Fixed)
+}
+
+fn time(function: &str, value: &Value) -> Result<DateTime, RuntimeError> {
+    match value {
+        Value::Time(time) => Ok(*time),
+        other => Err(type_error(function, "a time", other)),
+    }
+}
+
+fn whole(function: &str, value: &Value) -> Result<i64, RuntimeError> {
+    match number(function, value)? {
+        n if n.fract() == 0.0 => Ok(n as i64),
+        n => Err(RuntimeError::new(
+            ErrorKind::Value,
+            format!("'{}' expects whole numbers, got {}", function, n),
+        )),
+    }
+}
+
+// A piece of a time format: a token such as `YYYY`, or text that is copied as it is.
+enum Piece<'a> {
+    Token(&'static str),
+    Text(&'a str),
+}
+
+// Text in square brackets is never read as tokens, so `[Class at] HH:mm` gives
+// `Class at 14:07`. A `[` without a closing `]` is an ordinary character.
+fn pieces(format: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if let Some(end) = rest.strip_prefix('[').and_then(|inside| inside.find(']')) {
+            pieces.push(Piece::Text(&rest[1..end + 1]));
+            rest = &rest[end + 2..];
+        } else if let Some(token) = FORMAT_TOKENS.iter().find(|token| rest.starts_with(*token)) {
+            pieces.push(Piece::Token(token));
+            rest = &rest[token.len()..];
+        } else {
+            pieces.push(Piece::Text(&rest[..c.len_utf8()]));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    pieces
+}
+
+// A count of some unit, such as days, as a duration.
+fn span(function: &str, value: &Value, unit: i64) -> Result<Duration, RuntimeError> {
+    Ok(Duration((number(function, value)? * unit as f64).round() as i64))
+}
+
+fn read_digits(text: &mut &str, max: usize) -> Option<i64> {
+    let length = text.chars().take(max).take_while(|c| c.is_ascii_digit()).count();
+    if length == 0 {
+        return None;
+    }
+    let value = text[..length].parse().ok()?;
+    *text = &text[length..];
+    Some(value)
+}
+
+// Matches a month or weekday name, in full or cut to `length` letters, ignoring case.
+fn read_name(text: &mut &str, names: &[&str], length: Option<usize>) -> Option<usize> {
+    names.iter().position(|name| {
+        let name = &name[..length.unwrap_or(name.len())];
+        match text.get(..name.len()) {
+            Some(start) if start.eq_ignore_ascii_case(name) => {
+                *text = &text[name.len()..];
+                true
+            }
+            _ => false,
+        }
+    })
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 for a date in the proleptic Gregorian calendar, and back.
+// These are Howard Hinnant's `days_from_civil` and `civil_from_days`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> DateTime {
+        DateTime::parse(text, "YYYY-MM-DD HH:mm:ss").unwrap()
+    }
+
+    #[test]
+    fn days_convert_to_dates_and_back() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+
+        // Every day from year 1 to 9999 follows the one before it
+        let (first, last) = (days_from_civil(1, 1, 1), days_from_civil(9999, 12, 31));
+        let mut previous = civil_from_days(first);
+        assert_eq!(previous, (1, 1, 1));
+        for days in first + 1..=last {
+            let (year, month, day) = civil_from_days(days);
+            let next = if previous.2 < days_in_month(previous.0, previous.1) {
+                (previous.0, previous.1, previous.2 + 1)
+            } else if previous.1 < 12 {
+                (previous.0, previous.1 + 1, 1)
+            } else {
+                (previous.0 + 1, 1, 1)
+            };
+            assert_eq!((year, month, day), next);
+            assert_eq!(days_from_civil(year, month, day), days);
+            previous = next;
+        }
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(is_leap_year(2024) && is_leap_year(2000));
+        assert!(!is_leap_year(2023) && !is_leap_year(1900));
+        assert_eq!(time("2024-02-29 00:00:00").format("dddd"), "Thursday");
+        assert_eq!(DateTime::parse("2023-02-29", "YYYY-MM-DD"), None);
+        assert_eq!(DateTime::parse("1900-02-29", "YYYY-MM-DD"), None);
+        assert_eq!(time("2024-02-28 12:00:00").add(Duration(MILLIS_PER_DAY)).to_string(), "2024-02-29 12:00:00");
+    }
+
+    #[test]
+    fn formats_copy_bracketed_text() {
+        let class = time("2024-03-05 14:07:09");
+        assert_eq!(class.format("Class at HH:mm"), "Cla09 at 14:07");
+        assert_eq!(class.format("[Class at] HH:mm"), "Class at 14:07");
+        assert_eq!(class.format("[YYYY] YYYY [oops"), "YYYY 2024 [oops");
+        assert_eq!(DateTime::parse("Class at 14:07", "[Class at] HH:mm"), Some(time("1970-01-01 14:07:00")));
+        assert_eq!(DateTime::parse("Lesson at 14:07", "[Class at] HH:mm"), None);
+    }
+
+    #[test]
+    fn names_and_short_years_are_parsed() {
+        let expected = Some(time("2024-03-05 00:00:00"));
+        assert_eq!(DateTime::parse("Tue, 05 Mar 24", "ddd, DD MMM YY"), expected);
+        assert_eq!(DateTime::parse("tuesday 05 MARCH 2024", "dddd DD MMMM YYYY"), expected);
+        assert_eq!(DateTime::parse("Tuesday 05 Marc 2024", "dddd DD MMMM YYYY"), None);
+        assert_eq!(DateTime::parse("Tusday 05 Mar 24", "dddd DD MMM YY"), None);
+        assert_eq!(time("2024-03-05 14:07:09").format("ddd, DD MMM YY"), "Tue, 05 Mar 24");
+    }
+
+    #[test]
+    fn durations_show_their_sign_and_units() {
+        assert_eq!(Duration(0).to_string(), "0 seconds");
+        assert_eq!(Duration(-MILLIS_PER_DAY).to_string(), "-1 day");
+        assert_eq!(Duration(-(2 * MILLIS_PER_DAY + MILLIS_PER_MINUTE + 1500)).to_string(), "-2 days 1 minute 1.5 seconds");
+        assert_eq!(time("2024-03-05 00:00:00").since(time("2024-03-06 03:00:00")).to_string(), "-1 day 3 hours");
+    }
+}
@@ -0,0 +1,95 @@
+use super::{Host, PathAccess, define, permission_denied, string};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::value::{Object, Value};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+// Relative paths are taken from the directory the interpreter was started in.
+pub fn module() -> Object {
+    let mut file_system = Object::new();
+
+    define(&mut file_system, "read_file", 1, Some(1), |host, args| {
+        let path = readable(host, "read_file", &args[0])?;
+        let content = fs::read_to_string(path).map_err(|err| io_error("read", path, err))?;
+        Ok(Value::String(content))
+    });
+    // The file's lines, without their line endings
+    define(&mut file_system, "read_lines", 1, Some(1), |host, args| {
+        let path = readable(host, "read_lines", &args[0])?;
+        let content = fs::read_to_string(path).map_err(|err| io_error("read", path, err))?;
+        Ok(Value::list(content.lines().map(|line| Value::String(line.to_string())).collect()))
+    });
+    define(&mut file_system, "file_exists", 1, Some(1), |host, args| {
+        Ok(Value::Boolean(readable(host, "file_exists", &args[0])?.exists()))
+    });
+    // Entry names, sorted so the order is the same on every system
+    define(&mut file_system, "list_directory", 1, Some(1), |host, args| {
+        let path = readable(host, "list_directory", &args[0])?;
+        let mut names = Vec::new();
+        for entry in fs::read_dir(path).map_err(|err| io_error("list", path, err))? {
+            let entry = entry.map_err(|err| io_error("list", path, err))?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(Value::list(names.into_iter().map(Value::String).collect()))
+    });
+
+    // Anything that is not a string is written the way `show` prints it
+    define(&mut file_system, "write_file", 2, Some(2), |host, args| {
+        let path = writable(host, "write_file", &args[0])?;
+        fs::write(path, args[1].to_string()).map_err(|err| io_error("write", path, err))?;
+        Ok(Value::Nothing)
+    });
+    define(&mut file_system, "append_file", 2, Some(2), |host, args| {
+        let path = writable(host, "append_file", &args[0])?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(args[1].to_string().as_bytes()))
+            .map_err(|err| io_error("append to", path, err))?;
+        Ok(Value::Nothing)
+    });
+    define(&mut file_system, "delete_file", 1, Some(1), |host, args| {
+        let path = writable(host, "delete_file", &args[0])?;
+        fs::remove_file(path).map_err(|err| io_error("delete", path, err))?;
+        Ok(Value::Nothing)
+    });
+    // Missing parent directories are created too
+    define(&mut file_system, "create_directory", 1, Some(1), |host, args| {
+        let path = writable(host, "create_directory", &args[0])?;
+        fs::create_dir_all(path).map_err(|err| io_error("create", path, err))?;
+        Ok(Value::Nothing)
+    });
+
+    file_system
+}
+
+pub(super) fn readable<'a>(host: &Host, function: &str, value: &'a Value) -> Result<&'a Path, RuntimeError> {
+    allowed(&host.permissions.read, "read", function, value)
+}
+
+pub(super) fn writable<'a>(host: &Host, function: &str, value: &'a Value) -> Result<&'a Path, RuntimeError> {
+    allowed(&host.permissions.write, "write", function, value)
+}
+
+fn allowed<'a>(access: &PathAccess, action: &str, function: &str, value: &'a Value) -> Result<&'a Path, RuntimeError> {
+    let path = Path::new(string(function, value)?);
+    if access.allows(path) {
+        return Ok(path);
+    }
+    Err(permission_denied(
+        function,
+        action,
+        &path.display().to_string(),
+        &format!("--allow-{}", action),
+    ))
+}
+
+fn io_error(action: &str, path: &Path, err: io::Error) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Io,
+        format!("Cannot {} '{}': {}", action, path.display(), err),
+    )
+}
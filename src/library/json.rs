@@ -0,0 +1,464 @@
+use super::{define, string, type_error};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::value::{Object, Value};
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+// Deeper nesting is rejected instead of overflowing the interpreter's stack.
+const MAX_DEPTH: usize = 256;
+
+pub fn module() -> Object {
+    let mut json = Object::new();
+
+    // Arrays become lists, objects become objects and null becomes nothing
+    define(&mut json, "parse_json", 1, Some(1), |_, args| {
+        parse(string("parse_json", &args[0])?)
+            .map_err(|message| RuntimeError::new(ErrorKind::Value, format!("Invalid JSON: {}", message)))
+    });
+    // `to_json value` writes everything on one line; `to_json value, true` indents it
+    define(&mut json, "to_json", 1, Some(2), |_, args| {
+        let pretty = match args.get(1) {
+            Some(Value::Boolean(pretty)) => *pretty,
+            Some(other) => return Err(type_error("to_json", "a boolean", other)),
+            None => false,
+        };
+        Ok(Value::String(to_json(&args[0], pretty)?))
+    });
+    define(&mut json, "validate_json", 1, Some(1), |_, args| {
+        Ok(Value::Boolean(parse(string("validate_json", &args[0])?).is_ok()))
+    });
+
+    json
+}
+
+pub fn to_json(value: &Value, pretty: bool) -> Result<String, RuntimeError> {
+    let mut writer = Writer { output: String::new(), pretty, open: Vec::new() };
+    writer.write(value, 0)?;
+    Ok(writer.output)
+}
+
+fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { chars: text.chars().peekable(), line: 1, column: 1, depth: 0 };
+    parser.skip_whitespace();
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        None => Ok(value),
+        Some(_) => Err(parser.expected("the end of the text")),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    // Where the next character is, counting from 1
+    line: usize,
+    column: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.next();
+            return true;
+        }
+        false
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} (line {}, column {})", message, self.line, self.column)
+    }
+
+    fn expected(&mut self, what: &str) -> String {
+        match self.chars.peek().copied() {
+            Some(ch) => self.error(&format!("expected {}, found '{}'", what, ch.escape_debug())),
+            None => self.error(&format!("expected {}, found the end of the text", what)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
+        match self.chars.peek() {
+            Some('{') => self.nested(Self::parse_object),
+            Some('[') => self.nested(Self::parse_array),
+            Some('"') => Ok(Value::String(self.parse_string()?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", Value::Boolean(true)),
+            Some('f') => self.parse_literal("false", Value::Boolean(false)),
+            Some('n') => self.parse_literal("null", Value::Nothing),
+            _ => Err(self.expected("a value")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value, String>) -> Result<Value, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(&format!("nested more than {} levels deep", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<Value, String> {
+        self.next(); // Go Over the Opening Brace
+        let mut object = Object::new();
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(Value::object(object));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err(self.expected("a key in double quotes"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            if !self.eat(':') {
+                return Err(self.expected("':'"));
+            }
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            object.insert(key, value);
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(Value::object(object));
+            }
+            if !self.eat(',') {
+                return Err(self.expected("',' or '}'"));
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, String> {
+        self.next(); // Go Over the Opening Bracket
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Value::list(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(Value::list(items));
+            }
+            if !self.eat(',') {
+                return Err(self.expected("',' or ']'"));
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.next(); // Go Over the Opening Quote
+        let mut string = String::new();
+        loop {
+            match self.chars.peek().copied() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.next();
+                    return Ok(string);
+                }
+                Some('\\') => {
+                    self.next();
+                    string.push(self.parse_escape()?);
+                }
+                Some(ch) if ch < ' ' => return Err(self.error("control characters in strings must be escaped")),
+                Some(ch) => {
+                    self.next();
+                    string.push(ch);
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, String> {
+        let escaped = match self.chars.peek() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                self.next();
+                return self.parse_unicode();
+            }
+            _ => return Err(self.expected("an escape sequence")),
+        };
+        self.next();
+        Ok(escaped)
+    }
+
+    // Four hex digits, or two escapes in a row (a surrogate pair) for characters
+    // outside the basic plane, such as emoji
+    fn parse_unicode(&mut self) -> Result<char, String> {
+        let high = self.parse_hex()?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !(self.eat('\\') && self.eat('u')) {
+                    return Err(self.error("expected a second '\\u' escape to complete the surrogate pair"));
+                }
+                let low = self.parse_hex()?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(self.error("invalid surrogate pair"));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let Some(digit) = self.chars.peek().and_then(|ch| ch.to_digit(16)) else {
+                return Err(self.expected("a hex digit"));
+            };
+            self.next();
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    // JSON is stricter than Rust about numbers: no leading zeros, no leading '+',
+    // and digits on both sides of the decimal point.
+    fn parse_number(&mut self) -> Result<Value, String> {
+        let (line, column) = (self.line, self.column);
+        let mut number = String::new();
+        if self.eat('-') {
+            number.push('-');
+        }
+        if self.eat('0') {
+            number.push('0');
+        } else {
+            self.read_digits(&mut number)?;
+        }
+        if self.eat('.') {
+            number.push('.');
+            self.read_digits(&mut number)?;
+        }
+        if self.eat('e') || self.eat('E') {
+            number.push('e');
+            if let Some(&sign @ ('+' | '-')) = self.chars.peek() {
+                number.push(sign);
+                self.next();
+            }
+            self.read_digits(&mut number)?;
+        }
+        match number.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Value::Number(n)),
+            _ => Err(format!("number {} is too large (line {}, column {})", number, line, column)),
+        }
+    }
+
+    fn read_digits(&mut self, number: &mut String) -> Result<(), String> {
+        if !matches!(self.chars.peek(), Some('0'..='9')) {
+            return Err(self.expected("a digit"));
+        }
+        while let Some(&digit @ '0'..='9') = self.chars.peek() {
+            number.push(digit);
+            self.next();
+        }
+        Ok(())
+    }
+
+    fn parse_literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        for ch in word.chars() {
+            if !self.eat(ch) {
+                return Err(self.expected(&format!("'{}'", word)));
+            }
+        }
+        Ok(value)
+    }
+}
+
+// `open` holds the lists and objects being written, so one that contains itself is
+// reported instead of being written forever.
+struct Writer {
+    output: String,
+    pretty: bool,
+    open: Vec<*const ()>,
+}
+
+impl Writer {
+    fn write(&mut self, value: &Value, depth: usize) -> Result<(), RuntimeError> {
+        match value {
+            Value::Nothing => self.output.push_str("null"),
+            Value::Boolean(b) => self.output.push_str(if *b { "true" } else { "false" }),
+            Value::Number(n) if n.is_finite() => self.output.push_str(&n.to_string()),
+            Value::Number(n) => {
+                return Err(RuntimeError::new(ErrorKind::Value, format!("Cannot write {} as JSON", n)));
+            }
+            Value::String(s) => self.write_string(s),
+            // Times are written the way `show` prints them
+            Value::Time(time) => self.write_string(&time.to_string()),
+            Value::List(items) => {
+                self.enter(Rc::as_ptr(items) as *const ())?;
+                let items = items.borrow();
+                self.output.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push(',');
+                    }
+                    self.indent(depth + 1);
+                    self.write(item, depth + 1)?;
+                }
+                if !items.is_empty() {
+                    self.indent(depth);
+                }
+                self.output.push(']');
+                self.open.pop();
+            }
+            Value::Object(object) => {
+                self.enter(Rc::as_ptr(object) as *const ())?;
+                let object = object.borrow();
+                self.output.push('{');
+                for (i, (key, value)) in object.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push(',');
+                    }
+                    self.indent(depth + 1);
+                    self.write_string(key);
+                    self.output.push_str(if self.pretty { ": " } else { ":" });
+                    self.write(value, depth + 1)?;
+                }
+                if object.len() > 0 {
+                    self.indent(depth);
+                }
+                self.output.push('}');
+                self.open.pop();
+            }
+            other => return Err(type_error("to_json", "text, numbers, booleans, nothing, lists or objects", other)),
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, container: *const ()) -> Result<(), RuntimeError> {
+        if self.open.contains(&container) {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                "Cannot write a list or object that contains itself as JSON",
+            ));
+        }
+        self.open.push(container);
+        Ok(())
+    }
+
+    // Pretty output puts every item on its own line, two spaces deeper than its parent.
+    fn indent(&mut self, depth: usize) {
+        if self.pretty {
+            self.output.push('\n');
+            self.output.push_str(&"  ".repeat(depth));
+        }
+    }
+
+    fn write_string(&mut self, s: &str) {
+        self.output.push('"');
+        for ch in s.chars() {
+            match ch {
+                '"' => self.output.push_str("\\\""),
+                '\\' => self.output.push_str("\\\\"),
+                '\n' => self.output.push_str("\\n"),
+                '\r' => self.output.push_str("\\r"),
+                '\t' => self.output.push_str("\\t"),
+                '\u{8}' => self.output.push_str("\\b"),
+                '\u{c}' => self.output.push_str("\\f"),
+                ch if ch < ' ' => self.output.push_str(&format!("\\u{:04x}", ch as u32)),
+                ch => self.output.push(ch),
+            }
+        }
+        self.output.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn escapes_and_surrogate_pairs() {
+        assert_eq!(parse(r#""a\n\t\"\\\/\u00e9""#).unwrap(), text("a\n\t\"\\/é"));
+        assert_eq!(parse(r#""\ud83d\ude00""#).unwrap(), text("😀"));
+        assert_eq!(parse(r#""\ud83d""#).unwrap_err(), "expected a second '\\u' escape to complete the surrogate pair (line 1, column 8)");
+        assert_eq!(parse(r#""\ud83d\u0041""#).unwrap_err(), "invalid surrogate pair (line 1, column 14)");
+        assert_eq!(parse(r#""\udc00""#).unwrap_err(), "invalid unicode escape (line 1, column 8)");
+        assert_eq!(parse(r#""\x""#).unwrap_err(), "expected an escape sequence, found 'x' (line 1, column 3)");
+        assert_eq!(parse("\"a\u{1}\"").unwrap_err(), "control characters in strings must be escaped (line 1, column 3)");
+    }
+
+    #[test]
+    fn number_grammar() {
+        assert_eq!(parse("-12.5e2").unwrap(), Value::Number(-1250.0));
+        assert_eq!(parse("0").unwrap(), Value::Number(0.0));
+        assert_eq!(parse("01").unwrap_err(), "expected the end of the text, found '1' (line 1, column 2)");
+        assert_eq!(parse("1.").unwrap_err(), "expected a digit, found the end of the text (line 1, column 3)");
+        assert_eq!(parse("-").unwrap_err(), "expected a digit, found the end of the text (line 1, column 2)");
+        assert_eq!(parse("1e").unwrap_err(), "expected a digit, found the end of the text (line 1, column 3)");
+        assert_eq!(parse("+1").unwrap_err(), "expected a value, found '+' (line 1, column 1)");
+        assert_eq!(parse("[1, 1e400]").unwrap_err(), "number 1e400 is too large (line 1, column 5)");
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&nested(MAX_DEPTH + 1)).unwrap_err(),
+            format!("nested more than {} levels deep (line 1, column {})", MAX_DEPTH, MAX_DEPTH + 1)
+        );
+    }
+
+    #[test]
+    fn errors_point_at_their_line_and_column() {
+        let source = "{\n  \"a\": 1,\n  \"b\" 2\n}";
+        assert_eq!(parse(source).unwrap_err(), "expected ':', found '2' (line 3, column 7)");
+        assert_eq!(parse("[\n  1,\n").unwrap_err(), "expected a value, found the end of the text (line 3, column 1)");
+        assert_eq!(parse("{\"a\": 1}\n\n x").unwrap_err(), "expected the end of the text, found 'x' (line 3, column 2)");
+    }
+
+    #[test]
+    fn output_is_compact_or_indented() {
+        let mut object = Object::new();
+        object.insert("name".to_string(), text("Ada \"A\"\u{1}"));
+        object.insert("scores".to_string(), Value::list(vec![Value::Number(1.0), Value::Number(2.5)]));
+        object.insert("empty".to_string(), Value::list(Vec::new()));
+        object.insert("none".to_string(), Value::Nothing);
+        let value = Value::object(object);
+
+        assert_eq!(
+            to_json(&value, false).unwrap(),
+            r#"{"name":"Ada \"A\"\u0001","scores":[1,2.5],"empty":[],"none":null}"#
+        );
+        assert_eq!(
+            to_json(&value, true).unwrap(),
+            "{\n  \"name\": \"Ada \\\"A\\\"\\u0001\",\n  \"scores\": [\n    1,\n    2.5\n  ],\n  \"empty\": [],\n  \"none\": null\n}"
+        );
+        assert_eq!(parse(&to_json(&value, true).unwrap()).unwrap(), value);
+    }
+}
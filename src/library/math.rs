@@ -0,0 +1,110 @@
+use super::{Random, define, number, type_error, whole_number};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::value::{Object, Value};
+use std::cmp::Ordering;
+use std::f64::consts;
+
+pub fn module() -> Object {
+    let mut math = Object::new();
+    math.insert("pi".to_string(), Value::Number(consts::PI));
+    math.insert("e".to_string(), Value::Number(consts::E));
+
+    define(&mut math, "square_root", 1, Some(1), |_, args| {
+        let n = number("square_root", &args[0])?;
+        if n < 0.0 {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("Cannot take the square root of a negative number ({})", n),
+            ));
+        }
+        Ok(Value::Number(n.sqrt()))
+    });
+    define(&mut math, "power", 2, Some(2), |_, args| {
+        Ok(Value::Number(number("power", &args[0])?.powf(number("power", &args[1])?)))
+    });
+    define(&mut math, "absolute", 1, Some(1), |_, args| Ok(Value::Number(number("absolute", &args[0])?.abs())));
+    define(&mut math, "floor", 1, Some(1), |_, args| Ok(Value::Number(number("floor", &args[0])?.floor())));
+    define(&mut math, "ceiling", 1, Some(1), |_, args| Ok(Value::Number(number("ceiling", &args[0])?.ceil())));
+    // `round 2.5` gives 3; `round 3.14159, 2` keeps two decimals
+    define(&mut math, "round", 1, Some(2), |_, args| {
+        let n = number("round", &args[0])?;
+        let digits = match args.get(1) {
+            Some(digits) => number("round", digits)?,
+            None => 0.0,
+        };
+        let scale = 10f64.powf(digits);
+        Ok(Value::Number((n * scale).round() / scale))
+    });
+
+    define(&mut math, "sine", 1, Some(1), |_, args| Ok(Value::Number(number("sine", &args[0])?.sin())));
+    define(&mut math, "cosine", 1, Some(1), |_, args| Ok(Value::Number(number("cosine", &args[0])?.cos())));
+    define(&mut math, "tangent", 1, Some(1), |_, args| Ok(Value::Number(number("tangent", &args[0])?.tan())));
+    define(&mut math, "arcsine", 1, Some(1), |_, args| Ok(Value::Number(number("arcsine", &args[0])?.asin())));
+    define(&mut math, "arccosine", 1, Some(1), |_, args| Ok(Value::Number(number("arccosine", &args[0])?.acos())));
+    define(&mut math, "arctangent", 1, Some(1), |_, args| {
+        Ok(Value::Number(number("arctangent", &args[0])?.atan()))
+    });
+    // The natural logarithm, or the logarithm in another base: `log 8, 2`
+    define(&mut math, "log", 1, Some(2), |_, args| {
+        let n = number("log", &args[0])?;
+        if n <= 0.0 {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("Cannot take the logarithm of {}", n),
+            ));
+        }
+        match args.get(1) {
+            Some(base) => Ok(Value::Number(n.log(number("log", base)?))),
+            None => Ok(Value::Number(n.ln())),
+        }
+    });
+
+    define(&mut math, "minimum", 1, None, |_, args| extreme("minimum", args, Ordering::Less));
+    define(&mut math, "maximum", 1, None, |_, args| extreme("maximum", args, Ordering::Greater));
+
+    // Whole-number bounds give a whole number, both ends included; otherwise any
+    // number from `min` up to, but not including, `max`
+    define(&mut math, "random", 2, Some(2), |host, args| {
+        let min = number("random", &args[0])?;
+        let max = number("random", &args[1])?;
+        if min > max {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("Cannot pick a random number between {} and {}", min, max),
+            ));
+        }
+        let roll = host.random.next_float();
+        if min.fract() == 0.0 && max.fract() == 0.0 {
+            Ok(Value::Number(min + (roll * (max - min + 1.0)).floor()))
+        } else {
+            Ok(Value::Number(min + roll * (max - min)))
+        }
+    });
+    define(&mut math, "seed", 1, Some(1), |host, args| {
+        host.random = Random::new(whole_number("seed", &args[0])? as u64);
+        Ok(Value::Nothing)
+    });
+
+    math
+}
+
+// `minimum 3, 1, 2` or `minimum [3, 1, 2]`: the value that compares `wanted` against all others.
+fn extreme(function: &str, args: Vec<Value>, wanted: Ordering) -> Result<Value, RuntimeError> {
+    let items = match args.as_slice() {
+        [Value::List(items)] => items.borrow().clone(),
+        [single] if !matches!(single, Value::Number(_) | Value::String(_)) => {
+            return Err(type_error(function, "numbers or a list", single));
+        }
+        _ => args,
+    };
+    let mut items = items.into_iter();
+    let mut best = items.next().ok_or_else(|| {
+        RuntimeError::new(ErrorKind::Value, format!("Cannot take the {} of an empty list", function))
+    })?;
+    for item in items {
+        if item.compare(&best)? == wanted {
+            best = item;
+        }
+    }
+    Ok(best)
+}
@@ -0,0 +1,275 @@
+// Built-in libraries, written in Rust and imported like any other module.
+pub mod date_time;
+pub mod file_system;
+pub mod json;
+pub mod math;
+pub mod network;
+pub mod string_utils;
+pub mod system;
+
+use crate::error::{ErrorKind, RuntimeError};
+use crate::value::{Builtin, BuiltinFn, Object, Value};
+use date_time::Clock;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// What the built-in libraries share between calls, and the settings they run under.
+pub struct Host {
+    pub random: Random,
+    pub permissions: Permissions,
+    pub clock: Clock,
+    // How long a network request may wait to connect, or for more data
+    pub timeout: Duration,
+}
+
+impl Host {
+    pub fn new() -> Self {
+        Host {
+            random: Random::from_clock(),
+            permissions: Permissions::default(),
+            clock: Clock::System,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+// What a program may touch outside itself. Everything is denied unless the command
+// line grants it.
+#[derive(Default)]
+pub struct Permissions {
+    pub read: PathAccess,
+    pub write: PathAccess,
+    pub env: NameAccess,
+    // Hosts, as `example.com` or with a port, `localhost:8080`
+    pub net: NameAccess,
+    // Commands go through the shell, so running them cannot be limited to some programs
+    pub run: bool,
+}
+
+#[derive(Default)]
+pub enum PathAccess {
+    #[default]
+    Denied,
+    Everywhere,
+    // Only these directories and everything below them
+    Within(Vec<PathBuf>),
+}
+
+impl PathAccess {
+    // `--allow-read` grants everything; `--allow-read=./data,./config` only those paths.
+    pub fn grant(&mut self, paths: Option<&str>) {
+        let Some(paths) = paths else {
+            *self = PathAccess::Everywhere;
+            return;
+        };
+        let granted = paths.split(',').filter(|path| !path.is_empty()).map(|path| absolute_path(Path::new(path)));
+        match self {
+            PathAccess::Everywhere => {}
+            PathAccess::Within(existing) => existing.extend(granted),
+            PathAccess::Denied => *self = PathAccess::Within(granted.collect()),
+        }
+    }
+
+    pub fn allows(&self, path: &Path) -> bool {
+        match self {
+            PathAccess::Denied => false,
+            PathAccess::Everywhere => true,
+            PathAccess::Within(roots) => {
+                let path = absolute_path(path);
+                roots.iter().any(|root| path.starts_with(root))
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub enum NameAccess {
+    #[default]
+    Denied,
+    Everything,
+    Only(Vec<String>),
+}
+
+impl NameAccess {
+    // `--allow-env` grants every variable; `--allow-env=HOME,LANG` only those.
+    pub fn grant(&mut self, names: Option<&str>) {
+        let Some(names) = names else {
+            *self = NameAccess::Everything;
+            return;
+        };
+        let granted = names.split(',').filter(|name| !name.is_empty()).map(str::to_string);
+        match self {
+            NameAccess::Everything => {}
+            NameAccess::Only(existing) => existing.extend(granted),
+            NameAccess::Denied => *self = NameAccess::Only(granted.collect()),
+        }
+    }
+
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            NameAccess::Denied => false,
+            NameAccess::Everything => true,
+            NameAccess::Only(names) => names.iter().any(|granted| granted == name),
+        }
+    }
+}
+
+// Resolves `.`, `..` and symbolic links, so `./data/../secret.txt` cannot slip past a
+// check on `./data`. The path does not have to exist yet.
+fn absolute_path(path: &Path) -> PathBuf {
+    let current = env::current_dir().unwrap_or_default();
+    resolve(fs::canonicalize(&current).unwrap_or(current), path, 0)
+}
+
+// Links are followed as soon as they are reached, the way the system does, so a `..`
+// after a link leaves the directory the link points to rather than the link's own.
+fn resolve(mut resolved: PathBuf, path: &Path, links: usize) -> PathBuf {
+    // The system gives up on longer chains too, so the path cannot be opened anyway
+    const MAX_LINKS: usize = 40;
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => {
+                resolved.push(other);
+                if let Ok(canonical) = fs::canonicalize(&resolved) {
+                    resolved = canonical;
+                } else if let Ok(target) = fs::read_link(&resolved)
+                    && links < MAX_LINKS
+                {
+                    // A link to something missing: writing through it would create the target
+                    resolved.pop();
+                    resolved = resolve(resolved, &target, links + 1);
+                }
+            }
+        }
+    }
+    resolved
+}
+
+// A small seedable generator (SplitMix64). The same seed always gives the same numbers,
+// so programs that use randomness can be replayed with `--seed`.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    pub fn from_clock() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Random::new(nanos)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number in [0, 1).
+    pub fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// Adds a built-in function to a module namespace. `max_args` is `None` when any
+// number of extra arguments is accepted.
+fn define(module: &mut Object, name: &'static str, min_args: usize, max_args: Option<usize>, run: BuiltinFn) {
+    let builtin = Builtin { name, min_args, max_args, run };
+    module.insert(name.to_string(), Value::Builtin(Rc::new(builtin)));
+}
+
+fn type_error(function: &str, expected: &str, found: &Value) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Type,
+        format!("Type error: '{}' expects {}, found {}", function, expected, found.type_name()),
+    )
+}
+
+// `flag` is the command-line option that would have allowed the call.
+fn permission_denied(function: &str, action: &str, target: &str, flag: &str) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Permission,
+        format!(
+            "Permission denied: '{}' cannot {} '{}' (grant access with {})",
+            function, action, target, flag
+        ),
+    )
+}
+
+fn number(function: &str, value: &Value) -> Result<f64, RuntimeError> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        other => Err(type_error(function, "a number", other)),
+    }
+}
+
+fn string<'a>(function: &str, value: &'a Value) -> Result<&'a str, RuntimeError> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(type_error(function, "a string", other)),
+    }
+}
+
+// A position or count, which has to be a whole number of at least 0.
+fn whole_number(function: &str, value: &Value) -> Result<usize, RuntimeError> {
+    match number(function, value)? {
+        n if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        n => Err(RuntimeError::new(
+            ErrorKind::Value,
+            format!("'{}' expects a whole number of at least 0, got {}", function, n),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScratchDir;
+
+    #[test]
+    fn access_within_allows_paths_below_the_root_only() {
+        let root = ScratchDir::new("within");
+        fs::create_dir_all(root.join("data")).unwrap();
+        let mut access = PathAccess::default();
+        access.grant(Some(root.join("data").to_str().unwrap()));
+
+        assert!(access.allows(&root.join("data/report.txt")));
+        assert!(access.allows(&root.join("data/new/deeper.txt")));
+        assert!(!access.allows(&root.join("data/../secret.txt")));
+        assert!(!access.allows(&root.join("database.txt")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn access_within_follows_links_before_parent_components() {
+        use std::os::unix::fs::symlink;
+
+        let root = ScratchDir::new("links");
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::create_dir_all(root.join("outside/inner")).unwrap();
+        fs::write(root.join("outside/secret.txt"), "secret").unwrap();
+        symlink("../outside/inner", root.join("data/link")).unwrap();
+        symlink("../outside/new.txt", root.join("data/dangling")).unwrap();
+        let mut access = PathAccess::default();
+        access.grant(Some(root.join("data").to_str().unwrap()));
+
+        // `data/link/..` is `outside`, not `data`
+        assert!(!access.allows(&root.join("data/link/../secret.txt")));
+        assert!(!access.allows(&root.join("data/link/file.txt")));
+        // Writing through a link to a missing file would create it outside
+        assert!(!access.allows(&root.join("data/dangling")));
+    }
+}
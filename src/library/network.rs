@@ -0,0 +1,502 @@
+use super::file_system::{readable, writable};
+use super::{Host, define, number, permission_denied, string, type_error};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::library::json;
+use crate::value::{Object, Value};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+// The largest response body that is read (64 MiB), so a server cannot exhaust memory.
+const MAX_BODY: u64 = 64 << 20;
+
+// A plain HTTP/1.1 client. There is no TLS, so only `http://` URLs work, and
+// redirects are returned as they are rather than followed.
+pub fn module() -> Object {
+    let mut network = Object::new();
+
+    // `http_get url` or `http_get url, headers`, where headers is an object
+    define(&mut network, "http_get", 1, Some(2), |host, args| {
+        let headers = extra_headers("http_get", args.get(1))?;
+        let response = request(host, "http_get", "GET", &args[0], headers, Vec::new())?;
+        Ok(response.into_value())
+    });
+    // Text is sent as it is; lists and objects are sent as JSON
+    define(&mut network, "http_post", 2, Some(3), |host, args| {
+        let mut headers = extra_headers("http_post", args.get(2))?;
+        let (content_type, body) = match &args[1] {
+            Value::List(_) | Value::Object(_) => ("application/json", json::to_json(&args[1], false)?),
+            other => ("text/plain; charset=utf-8", other.to_string()),
+        };
+        default_header(&mut headers, "Content-Type", content_type);
+        let response = request(host, "http_post", "POST", &args[0], headers, body.into_bytes())?;
+        Ok(response.into_value())
+    });
+    // The response body is saved byte for byte. A status other than 2xx is an error,
+    // so an error page is never saved in place of the file.
+    define(&mut network, "download_file", 2, Some(2), |host, args| {
+        let path = writable(host, "download_file", &args[1])?;
+        let response = request(host, "download_file", "GET", &args[0], Vec::new(), Vec::new())?;
+        if !(200..300).contains(&response.status) {
+            return Err(RuntimeError::new(
+                ErrorKind::Network,
+                format!("Download from '{}' failed with status {}", args[0], response.status),
+            ));
+        }
+        fs::write(path, &response.body).map_err(|err| {
+            RuntimeError::new(ErrorKind::Io, format!("Cannot write '{}': {}", path.display(), err))
+        })?;
+        Ok(Value::Nothing)
+    });
+    // Posts the file's bytes as the whole request body
+    define(&mut network, "upload_file", 2, Some(2), |host, args| {
+        let path = readable(host, "upload_file", &args[0])?;
+        let body = fs::read(path).map_err(|err| {
+            RuntimeError::new(ErrorKind::Io, format!("Cannot read '{}': {}", path.display(), err))
+        })?;
+        let headers = vec![("Content-Type".to_string(), "application/octet-stream".to_string())];
+        let response = request(host, "upload_file", "POST", &args[1], headers, body)?;
+        Ok(response.into_value())
+    });
+    // How many seconds a request may wait to connect, or for more data. The default is 30.
+    define(&mut network, "set_timeout", 1, Some(1), |host, args| {
+        let seconds = number("set_timeout", &args[0])?;
+        match Duration::try_from_secs_f64(seconds) {
+            Ok(timeout) if seconds > 0.0 => {
+                host.timeout = timeout;
+                Ok(Value::Nothing)
+            }
+            _ => Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("'set_timeout' expects a number of seconds above 0, got {}", seconds),
+            )),
+        }
+    });
+
+    network
+}
+
+struct Url {
+    host: String,
+    port: u16,
+    // The host as written in the URL, with the port if one was given
+    authority: String,
+    // Starts with '/' and keeps the query
+    target: String,
+}
+
+fn parse_url(function: &str, value: &Value) -> Result<Url, RuntimeError> {
+    let text = string(function, value)?;
+    let invalid = |reason: &str| RuntimeError::new(ErrorKind::Value, format!("Invalid URL '{}': {}", text, reason));
+    let Some(rest) = text.strip_prefix("http://") else {
+        return Err(invalid(if text.starts_with("https://") {
+            "https is not supported, only http"
+        } else {
+            "it must start with http://"
+        }));
+    };
+    if rest.contains(char::is_whitespace) {
+        return Err(invalid("it cannot contain spaces"));
+    }
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (authority, target) = match rest.find(['/', '?']) {
+        Some(start) => (&rest[..start], &rest[start..]),
+        None => (rest, ""),
+    };
+    if authority.contains('@') {
+        return Err(invalid("user names and passwords are not supported"));
+    }
+
+    // IPv6 addresses are written in brackets, so their colons are not read as a port
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, after) = bracketed.split_once(']').ok_or_else(|| invalid("missing ']'"))?;
+            match after {
+                "" => (host, None),
+                _ => (host, Some(after.strip_prefix(':').ok_or_else(|| invalid("expected ':' after ']'"))?)),
+            }
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    if host.is_empty() {
+        return Err(invalid("it has no host"));
+    }
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid("the port must be a number from 0 to 65535"))?,
+        None => 80,
+    };
+    let target = if target.starts_with('/') { target.to_string() } else { format!("/{}", target) };
+    Ok(Url {
+        host: host.to_string(),
+        port,
+        authority: authority.to_string(),
+        target,
+    })
+}
+
+struct Response {
+    status: u16,
+    // Names in lowercase, as servers differ in how they write them
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    // `{ status, headers, body }`. Header names use `_` for `-`, so they can be read as
+    // properties: `response.headers.content_type`. A header sent more than once has its
+    // values joined with ", ".
+    fn into_value(self) -> Value {
+        let mut headers = Object::new();
+        for (name, value) in self.headers {
+            let name = name.replace('-', "_");
+            let value = match headers.get(&name) {
+                Some(earlier) => format!("{}, {}", earlier, value),
+                None => value,
+            };
+            headers.insert(name, Value::String(value));
+        }
+        let mut response = Object::new();
+        response.insert("status".to_string(), Value::Number(self.status as f64));
+        response.insert("headers".to_string(), Value::object(headers));
+        // A body that is valid UTF-8, as almost all are, is moved rather than copied
+        let body = String::from_utf8(self.body).unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
+        response.insert("body".to_string(), Value::String(body));
+        Value::object(response)
+    }
+}
+
+fn request(
+    host: &Host,
+    function: &str,
+    method: &str,
+    url: &Value,
+    mut headers: Vec<(String, String)>,
+    body: Vec<u8>,
+) -> Result<Response, RuntimeError> {
+    let parsed = parse_url(function, url)?;
+    let with_port = format!("{}:{}", parsed.host, parsed.port);
+    if !host.permissions.net.allows(&parsed.host) && !host.permissions.net.allows(&with_port) {
+        return Err(permission_denied(function, "connect to", &with_port, "--allow-net"));
+    }
+
+    default_header(&mut headers, "Host", &parsed.authority);
+    default_header(&mut headers, "User-Agent", concat!("delta/", env!("CARGO_PKG_VERSION")));
+    default_header(&mut headers, "Accept", "*/*");
+    if method == "POST" {
+        default_header(&mut headers, "Content-Length", &body.len().to_string());
+    }
+    // One request per connection, so the response ends where the connection does
+    headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Connection"));
+    headers.push(("Connection".to_string(), "close".to_string()));
+
+    send(&parsed, method, &headers, &body, host.timeout).map_err(|err| {
+        let message = match err.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                format!("Request to '{}' timed out after {} second(s)", url, host.timeout.as_secs_f64())
+            }
+            _ => format!("Request to '{}' failed: {}", url, err),
+        };
+        RuntimeError::new(ErrorKind::Network, message)
+    })
+}
+
+fn send(url: &Url, method: &str, headers: &[(String, String)], body: &[u8], timeout: Duration) -> io::Result<Response> {
+    let mut stream = connect(url, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut head = format!("{} {} HTTP/1.1\r\n", method, url.target);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    read_response(BufReader::new(stream))
+}
+
+// Tries each address the host resolves to, as a name may have both IPv4 and IPv6 ones.
+fn connect(url: &Url, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("cannot find host '{}'", url.host));
+    for address in (url.host.as_str(), url.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
+
+fn read_response(mut reader: impl BufRead) -> io::Result<Response> {
+    let status_line = read_line(&mut reader)?;
+    let status = match status_line.split(' ').collect::<Vec<_>>().as_slice() {
+        [version, code, ..] if version.starts_with("HTTP/") => code.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| malformed(&format!("unexpected status line '{}'", status_line)))?;
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':').ok_or_else(|| malformed(&format!("bad header '{}'", line)))?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+    let header = |wanted: &str| headers.iter().find(|(name, _)| name == wanted).map(|(_, value)| value.as_str());
+
+    let mut body = Vec::new();
+    if header("transfer-encoding").is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked")) {
+        read_chunks(&mut reader, &mut body)?;
+    } else if let Some(length) = header("content-length") {
+        let length: u64 = length.parse().map_err(|_| malformed(&format!("bad content length '{}'", length)))?;
+        if length > MAX_BODY {
+            return Err(too_large());
+        }
+        reader.take(length).read_to_end(&mut body)?;
+        if (body.len() as u64) < length {
+            return Err(malformed("the connection closed before the whole body arrived"));
+        }
+    } else {
+        reader.take(MAX_BODY + 1).read_to_end(&mut body)?;
+        if body.len() as u64 > MAX_BODY {
+            return Err(too_large());
+        }
+    }
+    Ok(Response { status, headers, body })
+}
+
+// Each chunk is its size in hex on a line of its own, then the data. A size of 0 ends
+// the body, followed by optional trailer lines that are skipped. The body only grows by
+// what actually arrives, whatever size a chunk claims.
+fn read_chunks(reader: &mut impl BufRead, body: &mut Vec<u8>) -> io::Result<()> {
+    loop {
+        let line = read_line(reader)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size, 16).map_err(|_| malformed(&format!("bad chunk size '{}'", size)))?;
+        if size == 0 {
+            while !read_line(reader)?.is_empty() {}
+            return Ok(());
+        }
+        if (body.len() as u64).checked_add(size).is_none_or(|total| total > MAX_BODY) {
+            return Err(too_large());
+        }
+        if ((&mut *reader).take(size).read_to_end(body)? as u64) < size {
+            return Err(malformed("the connection closed in the middle of a chunk"));
+        }
+        if !read_line(reader)?.is_empty() {
+            return Err(malformed("a chunk is longer than its size"));
+        }
+    }
+}
+
+// A line without its "\r\n". Running out of data in the middle of the head is an error.
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Err(malformed("the connection closed before the response was complete"));
+    }
+    let line = String::from_utf8_lossy(&line);
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn malformed(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid response, {}", reason))
+}
+
+fn too_large() -> io::Error {
+    malformed(&format!("the body is larger than {} bytes", MAX_BODY))
+}
+
+// Headers the program passes in, from an object such as `{ content_type: "text/csv" }`.
+// `_` in a name is sent as `-`.
+fn extra_headers(function: &str, value: Option<&Value>) -> Result<Vec<(String, String)>, RuntimeError> {
+    let object = match value {
+        Some(Value::Object(object)) => object.borrow(),
+        Some(other) => return Err(type_error(function, "an object of headers", other)),
+        None => return Ok(Vec::new()),
+    };
+    let mut headers = Vec::new();
+    for (name, value) in object.iter() {
+        let value = value.to_string();
+        let name = name.replace('_', "-");
+        let valid_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid_name || value.contains(['\r', '\n']) {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("'{}' cannot send the header '{}: {}'", function, name, value),
+            ));
+        }
+        headers.push((name, value));
+    }
+    Ok(headers)
+}
+
+// Headers the program set itself take priority over these.
+fn default_header(headers: &mut Vec<(String, String)>, name: &str, value: &str) {
+    if !headers.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(name)) {
+        headers.push((name.to_string(), value.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    fn call(host: &mut Host, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        match module().get(name) {
+            Some(Value::Builtin(builtin)) => (builtin.run)(host, args),
+            _ => panic!("network has no function '{}'", name),
+        }
+    }
+
+    fn property(value: &Value, name: &str) -> Value {
+        match value {
+            Value::Object(object) => object.borrow().get(name).cloned().expect("missing property"),
+            other => panic!("expected an object, found {}", other.type_name()),
+        }
+    }
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    fn allowed_host() -> Host {
+        let mut host = Host::new();
+        host.permissions.net.grant(Some("127.0.0.1"));
+        host
+    }
+
+    // A server on a free local port that answers one connection with `response`, after
+    // waiting `delay`. Joining the handle gives back the request it received.
+    fn serve(response: &str, delay: Duration) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let response = response.to_string();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request = String::new();
+            // Header lines until the blank one
+            while reader.read_line(&mut request).unwrap() > 2 {}
+            let length = request
+                .lines()
+                .find_map(|line| line.strip_prefix("Content-Length: "))
+                .map_or(0, |length| length.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+            thread::sleep(delay);
+            let _ = (&stream).write_all(response.as_bytes());
+            request
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn get_returns_status_headers_and_body() {
+        let (url, server) = serve(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello",
+            Duration::ZERO,
+        );
+        let response = call(&mut allowed_host(), "http_get", vec![text(&format!("{}/items?page=2", url))]).unwrap();
+
+        assert_eq!(property(&response, "status"), Value::Number(200.0));
+        assert_eq!(property(&property(&response, "headers"), "content_type"), text("text/plain"));
+        assert_eq!(property(&response, "body"), text("hello"));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /items?page=2 HTTP/1.1\r\n"));
+        assert!(request.contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn post_sends_objects_as_json() {
+        let (url, server) = serve("HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n", Duration::ZERO);
+        let mut item = Object::new();
+        item.insert("name".to_string(), text("pen"));
+        let response = call(&mut allowed_host(), "http_post", vec![text(&url), Value::object(item)]).unwrap();
+
+        assert_eq!(property(&response, "status"), Value::Number(201.0));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/json\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"name\":\"pen\"}"));
+    }
+
+    #[test]
+    fn chunked_bodies_are_joined() {
+        let (url, _server) = serve(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nhello \r\n5;note=x\r\nworld\r\n0\r\n\r\n",
+            Duration::ZERO,
+        );
+        let response = call(&mut allowed_host(), "http_get", vec![text(&url)]).unwrap();
+        assert_eq!(property(&response, "body"), text("hello world"));
+    }
+
+    #[test]
+    fn huge_chunk_sizes_are_rejected_without_allocating() {
+        let (url, _server) = serve(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nabc",
+            Duration::ZERO,
+        );
+        let error = call(&mut allowed_host(), "http_get", vec![text(&url)]).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Network);
+        assert!(error.message.contains("larger than"), "{}", error.message);
+    }
+
+    #[test]
+    fn bodies_over_the_limit_are_rejected() {
+        let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\nabc", MAX_BODY + 1);
+        let (url, _server) = serve(&response, Duration::ZERO);
+        let error = call(&mut allowed_host(), "http_get", vec![text(&url)]).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Network);
+        assert!(error.message.contains("larger than"), "{}", error.message);
+    }
+
+    #[test]
+    fn chunks_cut_short_are_malformed() {
+        let (url, _server) = serve("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n10\r\nabc", Duration::ZERO);
+        let error = call(&mut allowed_host(), "http_get", vec![text(&url)]).unwrap_err();
+        assert!(error.message.contains("in the middle of a chunk"), "{}", error.message);
+    }
+
+    #[test]
+    fn slow_servers_time_out() {
+        let (url, _server) = serve("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n", Duration::from_secs(2));
+        let mut host = allowed_host();
+        call(&mut host, "set_timeout", vec![Value::Number(0.2)]).unwrap();
+        let error = call(&mut host, "http_get", vec![text(&url)]).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Network);
+        assert!(error.message.contains("timed out"), "{}", error.message);
+    }
+
+    #[test]
+    fn timeouts_must_be_a_positive_duration() {
+        let mut host = Host::new();
+        for seconds in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e20] {
+            let error = call(&mut host, "set_timeout", vec![Value::Number(seconds)]).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Value, "{}", seconds);
+        }
+        assert_eq!(host.timeout, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn hosts_need_permission() {
+        let mut host = Host::new();
+        let error = call(&mut host, "http_get", vec![text("http://127.0.0.1:9/")]).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Permission);
+
+        host.permissions.net.grant(Some("example.com"));
+        let error = call(&mut host, "http_get", vec![text("http://127.0.0.1:9/")]).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Permission);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{define, string, type_error, whole_number};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::value::{Object, Value};
+
+// The longest string `repeat` and the padding functions build (64 MiB of text), so
+// a mistaken count raises an error instead of exhausting memory.
+const MAX_LENGTH: usize = 64 << 20;
+
+// Lengths and positions count characters, not bytes, and positions start at 0.
+pub fn module() -> Object {
+    let mut string_utils = Object::new();
+
+    define(&mut string_utils, "to_upper", 1, Some(1), |_, args| {
+        Ok(Value::String(string("to_upper", &args[0])?.to_uppercase()))
+    });
+    define(&mut string_utils, "to_lower", 1, Some(1), |_, args| {
+        Ok(Value::String(string("to_lower", &args[0])?.to_lowercase()))
+    });
+    define(&mut string_utils, "trim", 1, Some(1), |_, args| {
+        Ok(Value::String(string("trim", &args[0])?.trim().to_string()))
+    });
+    // An empty delimiter splits the text into characters
+    define(&mut string_utils, "split", 2, Some(2), |_, args| {
+        let text = string("split", &args[0])?;
+        let parts: Vec<Value> = match string("split", &args[1])? {
+            "" => text.chars().map(|c| Value::String(c.to_string())).collect(),
+            delimiter => text.split(delimiter).map(|part| Value::String(part.to_string())).collect(),
+        };
+        Ok(Value::list(parts))
+    });
+    define(&mut string_utils, "join", 2, Some(2), |_, args| {
+        let items = match &args[0] {
+            Value::List(items) => items.borrow(),
+            other => return Err(type_error("join", "a list", other)),
+        };
+        let parts: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+        Ok(Value::String(parts.join(string("join", &args[1])?)))
+    });
+    define(&mut string_utils, "replace", 3, Some(3), |_, args| {
+        let text = string("replace", &args[0])?;
+        let old = string("replace", &args[1])?;
+        if old.is_empty() {
+            return Err(RuntimeError::new(ErrorKind::Value, "'replace' cannot replace an empty string"));
+        }
+        Ok(Value::String(text.replace(old, string("replace", &args[2])?)))
+    });
+
+    define(&mut string_utils, "length", 1, Some(1), |_, args| {
+        Ok(Value::Number(string("length", &args[0])?.chars().count() as f64))
+    });
+    // `substring text, start` runs to the end; `substring text, start, end` stops before `end`
+    define(&mut string_utils, "substring", 2, Some(3), |_, args| {
+        let chars: Vec<char> = string("substring", &args[0])?.chars().collect();
+        let start = whole_number("substring", &args[1])?;
+        let end = match args.get(2) {
+            Some(end) => whole_number("substring", end)?,
+            None => chars.len(),
+        };
+        if start > end || end > chars.len() {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("'substring' range {} to {} is outside a string of length {}", start, end, chars.len()),
+            ));
+        }
+        Ok(Value::String(chars[start..end].iter().collect()))
+    });
+    // The position of the first match, or -1 when there is none
+    define(&mut string_utils, "index_of", 2, Some(2), |_, args| {
+        let text = string("index_of", &args[0])?;
+        let position = text
+            .find(string("index_of", &args[1])?)
+            .map_or(-1.0, |byte| text[..byte].chars().count() as f64);
+        Ok(Value::Number(position))
+    });
+    define(&mut string_utils, "repeat", 2, Some(2), |_, args| {
+        let text = string("repeat", &args[0])?;
+        let count = whole_number("repeat", &args[1])?;
+        check_length("repeat", text.len().checked_mul(count))?;
+        Ok(Value::String(text.repeat(count)))
+    });
+    define(&mut string_utils, "pad_left", 2, Some(3), |_, args| pad("pad_left", &args, true));
+    define(&mut string_utils, "pad_right", 2, Some(3), |_, args| pad("pad_right", &args, false));
+    define(&mut string_utils, "reverse", 1, Some(1), |_, args| {
+        Ok(Value::String(string("reverse", &args[0])?.chars().rev().collect()))
+    });
+
+    string_utils
+}
+
+// `pad_left text, width` or `pad_left text, width, "0"`. The fill defaults to a space.
+fn pad(function: &str, args: &[Value], left: bool) -> Result<Value, RuntimeError> {
+    let text = string(function, &args[0])?;
+    let width = whole_number(function, &args[1])?;
+    let fill = match args.get(2) {
+        Some(fill) => {
+            let fill = string(function, fill)?;
+            let mut chars = fill.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => {
+                    return Err(RuntimeError::new(
+                        ErrorKind::Value,
+                        format!("'{}' expects a single fill character, got \"{}\"", function, fill),
+                    ))
+                }
+            }
+        }
+        None => ' ',
+    };
+    let count = width.saturating_sub(text.chars().count());
+    check_length(function, count.checked_mul(fill.len_utf8()).and_then(|bytes| bytes.checked_add(text.len())))?;
+    let padding = fill.to_string().repeat(count);
+    Ok(Value::String(if left { padding + text } else { text.to_string() + &padding }))
+}
+
+// `bytes` is the size of the string about to be built, or `None` when even that overflowed.
+fn check_length(function: &str, bytes: Option<usize>) -> Result<(), RuntimeError> {
+    match bytes {
+        Some(bytes) if bytes <= MAX_LENGTH => Ok(()),
+        _ => Err(RuntimeError::new(
+            ErrorKind::Value,
+            format!("'{}' would build a string longer than {} bytes", function, MAX_LENGTH),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::Host;
+
+    fn call(name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let Some(Value::Builtin(builtin)) = module().get(name).cloned() else {
+            panic!("no function '{}'", name);
+        };
+        (builtin.run)(&mut Host::new(), args)
+    }
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn repeat_and_pad_build_strings_up_to_the_limit() {
+        assert_eq!(call("repeat", vec![text("ab"), Value::Number(3.0)]).unwrap(), text("ababab"));
+        assert_eq!(call("pad_left", vec![text("7"), Value::Number(3.0), text("0")]).unwrap(), text("007"));
+        assert_eq!(call("pad_right", vec![text("é"), Value::Number(3.0), text("·")]).unwrap(), text("é··"));
+
+        for (name, args) in [
+            ("repeat", vec![text("ab"), Value::Number(1e18)]),
+            ("repeat", vec![text("ab"), Value::Number(MAX_LENGTH as f64)]),
+            ("pad_left", vec![text("x"), Value::Number(1e15)]),
+            ("pad_right", vec![text("x"), Value::Number(1e19), text("é")]),
+        ] {
+            let error = call(name, args).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Value, "{}", name);
+            assert!(error.message.contains("longer than"), "{}", error.message);
+        }
+    }
+}
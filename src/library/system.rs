@@ -0,0 +1,81 @@
+use super::{Host, define, permission_denied, string, type_error};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::value::{Object, Value};
+use std::env;
+use std::process::Command;
+
+pub fn module() -> Object {
+    let mut system = Object::new();
+
+    // Runs through the shell and waits for it to finish. The result has `stdout`,
+    // `stderr` and `exit_code`, which is nothing when the command was killed by a signal.
+    define(&mut system, "run_command", 1, Some(1), |host, args| {
+        let command = string("run_command", &args[0])?;
+        if !host.permissions.run {
+            return Err(permission_denied("run_command", "run", command, "--allow-run"));
+        }
+        let output = shell(command)
+            .output()
+            .map_err(|err| RuntimeError::new(ErrorKind::Io, format!("Cannot run '{}': {}", command, err)))?;
+        let mut result = Object::new();
+        result.insert("stdout".to_string(), Value::String(String::from_utf8_lossy(&output.stdout).into_owned()));
+        result.insert("stderr".to_string(), Value::String(String::from_utf8_lossy(&output.stderr).into_owned()));
+        let exit_code = output.status.code().map_or(Value::Nothing, |code| Value::Number(code as f64));
+        result.insert("exit_code".to_string(), exit_code);
+        Ok(Value::object(result))
+    });
+
+    // Nothing when the variable is not set
+    define(&mut system, "get_environment", 1, Some(1), |host, args| {
+        let name = variable(host, "get_environment", "read", &args[0])?;
+        Ok(env::var_os(name).map_or(Value::Nothing, |value| Value::String(value.to_string_lossy().into_owned())))
+    });
+    // Commands run afterwards see the new value too
+    define(&mut system, "set_environment", 2, Some(2), |host, args| {
+        let name = variable(host, "set_environment", "set", &args[0])?;
+        let value = string("set_environment", &args[1])?;
+        if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("Cannot set the environment variable '{}'", name),
+            ));
+        }
+        // SAFETY: the interpreter runs on a single thread, so nothing reads the
+        // environment while it changes.
+        unsafe { env::set_var(name, value) };
+        Ok(Value::Nothing)
+    });
+
+    // `exit_program` stops with code 0; `exit_program 2` reports a failure to the caller
+    define(&mut system, "exit_program", 0, Some(1), |_, args| {
+        let code = match args.first() {
+            Some(Value::Number(n)) if n.fract() == 0.0 && (0.0..=255.0).contains(n) => *n as i32,
+            Some(Value::Number(n)) => {
+                return Err(RuntimeError::new(
+                    ErrorKind::Value,
+                    format!("'exit_program' expects a whole number from 0 to 255, got {}", n),
+                ));
+            }
+            Some(other) => return Err(type_error("exit_program", "a number", other)),
+            None => 0,
+        };
+        Err(RuntimeError::new(ErrorKind::Exit(code), format!("Exited with code {}", code)))
+    });
+
+    system
+}
+
+fn shell(command: &str) -> Command {
+    let (program, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut shell = Command::new(program);
+    shell.args([flag, command]);
+    shell
+}
+
+fn variable<'a>(host: &Host, function: &str, action: &str, value: &'a Value) -> Result<&'a str, RuntimeError> {
+    let name = string(function, value)?;
+    if host.permissions.env.allows(name) {
+        return Ok(name);
+    }
+    Err(permission_denied(function, action, name, "--allow-env"))
+}
@@ -1,25 +1,108 @@
 use std::env;
 use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
 use std::process;
+use std::thread;
 
 mod lexer;
 mod parser;
 mod ast;
 mod codegen;
+mod checker;
+mod value;
+mod error;
+mod modules;
+mod library;
+#[cfg(test)]
+mod testing;
 
 use lexer::Lexer;
 use parser::Parser;
 use codegen::CodeGenerator;
+use checker::Checker;
+use error::ErrorKind;
+use library::date_time::{self, Clock};
+use library::{Host, Permissions, Random};
+
+const USAGE: &str = "[--seed <number>] [--now <YYYY-MM-DD HH:mm:ss>] [--allow-read[=<paths>]] \
+                     [--allow-write[=<paths>]] [--allow-env[=<names>]] [--allow-net[=<hosts>]] \
+                     [--allow-run] <source_file.de>";
+
+// Flags come before the source file: `delta --seed 42 --allow-read=./data game.de`
+struct Options {
+    filename: String,
+    seed: Option<u64>,
+    // A fixed time for `date_time.now`, so output that depends on it can be tested
+    clock: Option<Clock>,
+    permissions: Permissions,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut filename = None;
+    let mut seed = None;
+    let mut clock = None;
+    let mut permissions = Permissions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // Values can follow an `=` or come as the next argument: `--seed=42`, `--seed 42`
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        match flag {
+            "--seed" => {
+                let value = value.or_else(|| args.next().map(String::as_str)).ok_or("'--seed' needs a number")?;
+                seed = Some(value.parse().map_err(|_| format!("Invalid seed '{}'", value))?);
+            }
+            "--now" => {
+                let value = value.or_else(|| args.next().map(String::as_str)).ok_or("'--now' needs a time")?;
+                clock = Some(date_time::fixed_clock(value).ok_or_else(|| format!("Invalid time '{}'", value))?);
+            }
+            "--allow-read" => permissions.read.grant(value),
+            "--allow-write" => permissions.write.grant(value),
+            "--allow-env" => permissions.env.grant(value),
+            "--allow-net" => permissions.net.grant(value),
+            "--allow-run" if value.is_none() => permissions.run = true,
+            "--allow-run" => return Err("'--allow-run' does not take a value".to_string()),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+            _ if filename.is_none() => filename = Some(arg.clone()),
+            _ => return Err("Only one source file can be run at a time".to_string()),
+        }
+    }
+    Ok(Options {
+        filename: filename.ok_or("No source file given")?,
+        seed,
+        clock,
+        permissions,
+    })
+}
 
 fn main() {
+    // Each Delta call takes several native frames, far more of them in debug builds, so
+    // the interpreter gets a stack sized for `MAX_CALL_DEPTH` rather than the default one
+    let interpreter = thread::Builder::new()
+        .stack_size(codegen::STACK_SIZE)
+        .spawn(run)
+        .expect("cannot start the interpreter thread");
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run() {
     let args: Vec<String> = env::args().collect();
     
-    if args.len() != 2 {
-        eprintln!("Usage: {} <source_file.de>", args[0]);
-        process::exit(1);
-    }
+    let options = match parse_options(&args[1..]) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("Usage: {} {}", args[0], USAGE);
+            process::exit(1);
+        }
+    };
     
-    let filename = &args[1];
+    let filename = &options.filename;
     let source = match fs::read_to_string(filename) {
         Ok(content) => content,
         Err(err) => {
@@ -50,13 +133,74 @@ fn main() {
         }
     };
     
-    // Step 3: Print AST (debug output)
+    // Step 3: Static checks
+    let mut checker = Checker::new();
+    checker.check(&ast);
+    for warning in checker.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    if !checker.errors().is_empty() {
+        for err in checker.errors() {
+            eprintln!("Check error: {}", err);
+        }
+        process::exit(1);
+    }
+    
+    // Step 4: Print AST (debug output)
     // println!("AST: {:#?}", ast);
     
-    // Step 4: For now, just interpret
-    let mut codegen = CodeGenerator::new();
-    if let Err(err) = codegen.interpret(&ast) {
-        eprintln!("Interpreter error: {}", err);
-        process::exit(1);
+    // Step 5: For now, just interpret
+    let mut host = Host::new();
+    host.permissions = options.permissions;
+    if let Some(clock) = options.clock {
+        host.clock = clock;
+    }
+    if let Some(seed) = options.seed {
+        host.random = Random::new(seed);
+    }
+    let mut codegen = CodeGenerator::new(host);
+    let code = match codegen.interpret(&ast, Path::new(filename)) {
+        Ok(()) => 0,
+        Err(err) => match err.kind {
+            ErrorKind::Exit(code) => code,
+            _ => {
+                eprintln!("Interpreter error: {}", err);
+                1
+            }
+        },
+    };
+    // `process::exit` skips destructors, so text that was printed without a newline
+    // has to be flushed first
+    let _ = io::stdout().flush();
+    process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        parse_options(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn a_seed_can_be_given_on_the_command_line() {
+        let parsed = options(&["--seed", "42", "game.de"]).unwrap();
+        assert_eq!((parsed.filename.as_str(), parsed.seed), ("game.de", Some(42)));
+        assert_eq!(options(&["--seed=7", "game.de"]).unwrap().seed, Some(7));
+
+        assert_eq!(options(&["--seed", "-1", "game.de"]).err().unwrap(), "Invalid seed '-1'");
+        assert_eq!(options(&["game.de", "--seed"]).err().unwrap(), "'--seed' needs a number");
+    }
+
+    #[test]
+    fn now_can_be_fixed_on_the_command_line() {
+        let clock = options(&["--now=2024-02-29 13:45:00", "game.de"]).unwrap().clock.unwrap();
+        assert_eq!(clock.now().format("YYYY-MM-DD HH:mm:ss"), "2024-02-29 13:45:00");
+        let midnight = options(&["--now", "2024-02-29", "game.de"]).unwrap().clock.unwrap();
+        assert_eq!(midnight.now().format("HH:mm:ss"), "00:00:00");
+
+        assert_eq!(options(&["--now=yesterday", "game.de"]).err().unwrap(), "Invalid time 'yesterday'");
+        assert_eq!(options(&["--now=2023-02-29", "game.de"]).err().unwrap(), "Invalid time '2023-02-29'");
     }
 }
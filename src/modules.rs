@@ -0,0 +1,55 @@
+use crate::library;
+use crate::value::Object;
+use std::env;
+use std::path::{Path, PathBuf};
+
+// Builds a built-in module's namespace the first time it is imported.
+pub type BuildModule = fn() -> Object;
+
+// Libraries compiled into the interpreter, by import name.
+const BUILTIN_MODULES: &[(&str, BuildModule)] = &[
+    ("date_time", library::date_time::module),
+    ("file_system", library::file_system::module),
+    ("json", library::json::module),
+    ("math", library::math::module),
+    ("network", library::network::module),
+    ("string_utils", library::string_utils::module),
+    ("system", library::system::module),
+];
+
+// Where an imported module was found.
+pub enum ModuleSource {
+    File(PathBuf),
+    Builtin(BuildModule),
+}
+
+// Looks for a module in the documented order: a `.de` file next to the importing file,
+// then a built-in library, then the directories listed in the DELTA_PATH variable.
+pub fn resolve(name: &str, base_dir: &Path) -> Option<ModuleSource> {
+    let file_name = if name.ends_with(".de") {
+        name.to_string()
+    } else {
+        format!("{}.de", name)
+    };
+
+    let local = base_dir.join(&file_name);
+    if local.is_file() {
+        return Some(ModuleSource::File(local));
+    }
+    if let Some((_, build)) = BUILTIN_MODULES.iter().find(|(builtin, _)| *builtin == name) {
+        return Some(ModuleSource::Builtin(*build));
+    }
+    let search_path = env::var_os("DELTA_PATH")?;
+    env::split_paths(&search_path)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+        .map(ModuleSource::File)
+}
+
+// The name a whole-module import is bound to: `user_manager` for `import "lib/user_manager"`.
+pub fn default_name(module: &str) -> String {
+    Path::new(module)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| module.to_string())
+}
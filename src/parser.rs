@@ -1,23 +1,36 @@
 use crate::ast::*;
-use crate::lexer::Token;
+use crate::lexer::{Lexer, SpannedToken, StringPart, Token, keyword_word};
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     current: usize,
+    function_depth: usize,
+    loop_depth: usize,
+    // Set when the error being returned already names its exact position, so `parse`
+    // leaves it as it is
+    error_located: bool,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+    pub fn new(tokens: Vec<SpannedToken>) -> Self {
+        Parser { tokens, current: 0, function_depth: 0, loop_depth: 0, error_located: false }
     }
 
     fn current_token(&self) -> &Token {
-        self.tokens.get(self.current).unwrap_or(&Token::Eof)
+        self.tokens.get(self.current).map(|t| &t.token).unwrap_or(&Token::Eof)
+    }
+
+    fn current_line(&self) -> usize {
+        self.tokens
+            .get(self.current)
+            .or(self.tokens.last())
+            .map(|t| t.line)
+            .unwrap_or(1)
     }
 
     fn advance(&mut self) -> &Token {
         if self.current < self.tokens.len() {
-            self.current = self.current + 1;
+            self.current += 1;
         }
         self.current_token()
     }
@@ -42,7 +55,14 @@ impl Parser {
         self.skip_newlines();
 
         while !matches!(self.current_token(), Token::Eof) {
-            statements.push(self.parse_statement()?);
+            let statement = self.parse_statement().map_err(|err| {
+                if self.error_located {
+                    err
+                } else {
+                    format!("{} at line {}", err, self.current_line())
+                }
+            })?;
+            statements.push(statement);
             self.skip_newlines();
         }
 
@@ -50,44 +70,89 @@ impl Parser {
     }
 
     fn parse_statement(&mut self) -> Result<Statement, String> {
-        match self.current_token() {
+        let line = self.current_line();
+        let kind = match self.current_token() {
             Token::Let => self.parse_let_statement(),
+            Token::Const => self.parse_const_statement(),
             Token::Show => self.parse_show_statement(),
             Token::When => self.parse_when_statement(),
             Token::Define => self.parse_function_def(),
+            Token::Return => self.parse_return_statement(),
+            Token::Repeat => self.parse_repeat_statement(),
+            Token::Break | Token::Continue => self.parse_loop_control(),
+            Token::Choose => self.parse_choose_statement(),
+            Token::Add => self.parse_add_statement(),
+            Token::Remove => self.parse_remove_statement(),
+            Token::Attempt => self.parse_attempt_statement(),
+            Token::Fail => self.parse_fail_statement(),
+            Token::Import => self.parse_import_statement(),
             _ => {
                 let expr = self.parse_expression()?;
-                Ok(Statement::Expression(expr))
+                Ok(StatementKind::Expression(expr))
             }
-        }
+        }?;
+        Ok(Statement { kind, line })
     }
 
-    fn parse_let_statement(&mut self) -> Result<Statement, String> {
+    fn parse_let_statement(&mut self) -> Result<StatementKind, String> {
         self.expect(Token::Let)?;
         
-        let identifier = match self.current_token() {
-            Token::Identifier(name) => {
-                let name = name.clone();
-                self.advance();
-                name
-            }
-            _ => return Err("Expected identifier after 'let'".to_string()),
-        };
+        // `let a, b be ...` unpacks several values at once
+        let mut targets = vec![self.parse_let_target()?];
+        while matches!(self.current_token(), Token::Comma) {
+            self.advance(); // Go Over Comma
+            targets.push(self.parse_let_target()?);
+        }
         
         self.expect(Token::Be)?;
         
         let value = self.parse_expression()?;
         
-        Ok(Statement::Let(LetStatement { identifier, value }))
+        Ok(StatementKind::Let(LetStatement { targets, value }))
+    }
+
+    fn parse_const_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Const)?;
+
+        if self.function_depth > 0 {
+            return Err("'const' is only allowed outside of functions".to_string());
+        }
+
+        let name = match self.current_token() {
+            Token::Identifier(name) => name.clone(),
+            _ => return Err("Expected identifier after 'const'".to_string()),
+        };
+        self.advance();
+
+        self.expect(Token::Be)?;
+        let value = self.parse_expression()?;
+
+        Ok(StatementKind::Const(ConstStatement { name, value }))
     }
 
-    fn parse_show_statement(&mut self) -> Result<Statement, String> {
+    fn parse_let_target(&mut self) -> Result<LetTarget, String> {
+        let name = match self.current_token() {
+            Token::Identifier(name) => name.clone(),
+            _ => return Err("Expected identifier after 'let'".to_string()),
+        };
+        self.advance();
+
+        if !matches!(self.current_token(), Token::Dot) {
+            return Ok(LetTarget::Identifier(name));
+        }
+        match self.parse_property_chain(Expression::Identifier(name))? {
+            Expression::Property(access) => Ok(LetTarget::Property(access)),
+            _ => unreachable!("a chain starting at a dot is a property access"),
+        }
+    }
+
+    fn parse_show_statement(&mut self) -> Result<StatementKind, String> {
         self.expect(Token::Show)?;
         let value = self.parse_expression()?;
-        Ok(Statement::Show(ShowStatement { value }))
+        Ok(StatementKind::Show(ShowStatement { value }))
     }
     
-    fn parse_when_statement(&mut self) -> Result<Statement, String> {
+    fn parse_when_statement(&mut self) -> Result<StatementKind, String> {
         self.expect(Token::When)?;
 
         let condition = self.parse_expression()?;
@@ -134,14 +199,315 @@ impl Parser {
         };
 
                 
-        Ok(Statement::When(WhenStatement {
+        Ok(StatementKind::When(WhenStatement {
             condition,
             then_block,
             otherwise_block,
         }))
     }
 
-    fn parse_function_def(&mut self) -> Result<Statement, String> {
+    // Parses an indented block of statements, consuming the closing dedent.
+    fn parse_block(&mut self, owner: &str) -> Result<Vec<Statement>, String> {
+        self.skip_newlines();
+        if !matches!(self.current_token(), Token::Indent) {
+            return Err(format!("Expected an indented block after '{}'", owner));
+        }
+        self.advance(); // Go Over Indent
+
+        let mut statements = Vec::new();
+        while !matches!(self.current_token(), Token::Dedent | Token::Eof) {
+            statements.push(self.parse_statement()?);
+            self.skip_newlines();
+        }
+
+        if matches!(self.current_token(), Token::Dedent) {
+            self.advance(); // Go Over Dedent
+        }
+        Ok(statements)
+    }
+
+    fn parse_loop_body(&mut self, owner: &str) -> Result<Vec<Statement>, String> {
+        self.loop_depth += 1;
+        let body = self.parse_block(owner);
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn parse_repeat_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Repeat)?;
+
+        if matches!(self.current_token(), Token::For) {
+            return self.parse_repeat_for_each();
+        }
+        self.expect(Token::While)?;
+
+        let condition = self.parse_expression()?;
+        let body = self.parse_loop_body("repeat while")?;
+
+        Ok(StatementKind::RepeatWhile(RepeatWhileStatement { condition, body }))
+    }
+
+    fn parse_repeat_for_each(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::For)?;
+        self.expect(Token::Each)?;
+
+        let variable = match self.current_token() {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance();
+                name
+            }
+            _ => return Err("Expected loop variable after 'repeat for each'".to_string()),
+        };
+
+        // `repeat for each item in <list>`
+        if matches!(self.current_token(), Token::In) {
+            self.advance(); // Go Over In
+            let iterable = self.parse_expression()?;
+            let body = self.parse_loop_body("repeat for each")?;
+            return Ok(StatementKind::RepeatEach(RepeatEachStatement { variable, iterable, body }));
+        }
+
+        self.expect(Token::From)?;
+        let start = self.parse_expression()?;
+        self.expect(Token::To)?;
+        let end = self.parse_expression()?;
+
+        let step = if matches!(self.current_token(), Token::By) {
+            self.advance(); // Go Over By
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        let body = self.parse_loop_body("repeat for each")?;
+
+        Ok(StatementKind::RepeatRange(RepeatRangeStatement {
+            variable,
+            start,
+            end,
+            step,
+            body,
+        }))
+    }
+
+    fn parse_choose_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Choose)?;
+        let subject = self.parse_expression()?;
+
+        self.skip_newlines();
+        if !matches!(self.current_token(), Token::Indent) {
+            return Err("Expected indented 'when' arms after 'choose'".to_string());
+        }
+        self.advance(); // Go Over Indent
+
+        let mut arms = Vec::new();
+        let mut otherwise_block = None;
+        while !matches!(self.current_token(), Token::Dedent | Token::Eof) {
+            match self.current_token() {
+                Token::When if otherwise_block.is_some() => {
+                    return Err("'otherwise' must be the last arm of 'choose'".to_string());
+                }
+                Token::When => {
+                    self.advance(); // Go Over When
+                    let value = self.parse_expression()?;
+                    let pattern = if matches!(self.current_token(), Token::To) {
+                        self.advance(); // Go Over To
+                        ChoosePattern::Range(value, self.parse_expression()?)
+                    } else {
+                        ChoosePattern::Value(value)
+                    };
+                    if matches!(self.current_token(), Token::Then) {
+                        self.advance();
+                    }
+                    let body = self.parse_block("when")?;
+                    arms.push(ChooseArm { pattern, body });
+                }
+                Token::Otherwise if otherwise_block.is_none() => {
+                    self.advance(); // Go Over Otherwise
+                    otherwise_block = Some(self.parse_block("otherwise")?);
+                }
+                other => return Err(format!("Expected 'when' or 'otherwise' in 'choose', found {:?}", other)),
+            }
+            self.skip_newlines();
+        }
+
+        if matches!(self.current_token(), Token::Dedent) {
+            self.advance(); // Go Over Dedent
+        }
+
+        Ok(StatementKind::Choose(ChooseStatement {
+            subject,
+            arms,
+            otherwise_block,
+        }))
+    }
+
+    fn parse_attempt_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Attempt)?;
+        let body = self.parse_block("attempt")?;
+        self.skip_newlines();
+
+        let rescue = if matches!(self.current_token(), Token::Rescue) {
+            self.advance(); // Go Over Rescue
+            let variable = match self.current_token() {
+                Token::Identifier(name) => {
+                    let name = name.clone();
+                    self.advance();
+                    Some(name)
+                }
+                _ => None,
+            };
+            Some(RescueClause { variable, body: self.parse_block("rescue")? })
+        } else {
+            None
+        };
+        self.skip_newlines();
+
+        let always_block = if matches!(self.current_token(), Token::Always) {
+            self.advance(); // Go Over Always
+            Some(self.parse_block("always")?)
+        } else {
+            None
+        };
+
+        if rescue.is_none() && always_block.is_none() {
+            return Err("Expected 'rescue' or 'always' after the 'attempt' block".to_string());
+        }
+        Ok(StatementKind::Attempt(AttemptStatement { body, rescue, always_block }))
+    }
+
+    fn parse_fail_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Fail)?;
+        self.expect(Token::With)?;
+        let value = self.parse_expression()?;
+        Ok(StatementKind::Fail(FailStatement { value }))
+    }
+
+    fn parse_add_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Add)?;
+        let item = self.parse_expression()?;
+        self.expect(Token::To)?;
+        let list = self.parse_expression()?;
+        Ok(StatementKind::Add(AddStatement { item, list }))
+    }
+
+    fn parse_remove_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Remove)?;
+        let item = self.parse_expression()?;
+        self.expect(Token::From)?;
+        let list = self.parse_expression()?;
+        Ok(StatementKind::Remove(RemoveStatement { item, list }))
+    }
+
+    fn parse_loop_control(&mut self) -> Result<StatementKind, String> {
+        let (statement, keyword) = match self.current_token() {
+            Token::Break => (StatementKind::Break, "break"),
+            _ => (StatementKind::Continue, "continue"),
+        };
+        self.advance();
+
+        if self.loop_depth == 0 {
+            return Err(format!("'{}' used outside of a loop", keyword));
+        }
+        Ok(statement)
+    }
+
+    fn parse_import_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Import)?;
+
+        if self.function_depth > 0 {
+            return Err("'import' is only allowed outside of functions".to_string());
+        }
+
+        // `import a, b as c from "module"` binds the listed names directly. A function
+        // named by a keyword, like `string_utils.length`, can only be imported under
+        // another name.
+        let mut names = Vec::new();
+        loop {
+            let (name, keyword) = match self.current_token() {
+                Token::Identifier(name) => (name.clone(), false),
+                token => match keyword_word(token) {
+                    Some(word) => (word.to_string(), true),
+                    None => break,
+                },
+            };
+            self.advance();
+            let alias = if matches!(self.current_token(), Token::As) {
+                self.advance(); // Go Over As
+                match self.current_token() {
+                    Token::Identifier(alias) => {
+                        let alias = alias.clone();
+                        self.advance();
+                        Some(alias)
+                    }
+                    other => return Err(format!("Expected a name after 'as', found {:?}", other)),
+                }
+            } else if keyword {
+                return Err(format!(
+                    "'{}' is a keyword, so it has to be imported under another name: '{} as <name>'",
+                    name, name
+                ));
+            } else {
+                None
+            };
+            names.push(ImportedName { name, alias });
+            if !matches!(self.current_token(), Token::Comma) {
+                break;
+            }
+            self.advance(); // Go Over Comma
+        }
+        if !names.is_empty() {
+            self.expect(Token::From)?;
+        }
+
+        let module = match self.current_token() {
+            Token::String(module) => module.clone(),
+            other => return Err(format!("Expected a module name in quotes after 'import', found {:?}", other)),
+        };
+        self.advance();
+
+        let alias = if matches!(self.current_token(), Token::As) {
+            if !names.is_empty() {
+                return Err("'as' cannot be used when importing names with 'from'".to_string());
+            }
+            self.advance(); // Go Over As
+            match self.current_token() {
+                Token::Identifier(alias) => {
+                    let alias = alias.clone();
+                    self.advance();
+                    Some(alias)
+                }
+                other => return Err(format!("Expected a name after 'as', found {:?}", other)),
+            }
+        } else {
+            None
+        };
+
+        Ok(StatementKind::Import(ImportStatement { module, alias, names }))
+    }
+
+    fn parse_return_statement(&mut self) -> Result<StatementKind, String> {
+        self.expect(Token::Return)?;
+
+        if self.function_depth == 0 {
+            return Err("'return' used outside of a function".to_string());
+        }
+
+        // `return a, b` hands several values back to the caller
+        let mut values = Vec::new();
+        if !matches!(self.current_token(), Token::Newline | Token::Dedent | Token::End | Token::Eof) {
+            values.push(self.parse_expression()?);
+            while matches!(self.current_token(), Token::Comma) {
+                self.advance(); // Go Over Comma
+                values.push(self.parse_expression()?);
+            }
+        }
+
+        Ok(StatementKind::Return(ReturnStatement { values }))
+    }
+
+    fn parse_function_def(&mut self) -> Result<StatementKind, String> {
         self.expect(Token::Define)?;
         
         let name = match self.current_token() {
@@ -153,24 +519,41 @@ impl Parser {
             _ => return Err("Expected function name after 'define'".to_string()),
         };
         
-        let mut parameters = Vec::new();
+        let mut parameters: Vec<Parameter> = Vec::new();
         
         if matches!(self.current_token(), Token::With) {
             self.advance(); // consume 'with'
             
             loop {
-                match self.current_token() {
-                    Token::Identifier(param) => {
-                        parameters.push(param.clone());
-                        self.advance();
-                    }
-                    _ => break,
+                let param = match self.current_token() {
+                    Token::Identifier(param) => param.clone(),
+                    other => return Err(format!("Expected parameter name in '{}', found {:?}", name, other)),
+                };
+                self.advance();
+
+                // `name be <value>` gives the parameter a default
+                let default = if matches!(self.current_token(), Token::Be) {
+                    self.advance();
+                    Some(self.parse_expression()?)
+                } else {
+                    None
+                };
+
+                if default.is_none() && parameters.iter().any(|p| p.default.is_some()) {
+                    return Err(format!(
+                        "Parameter '{}' of '{}' needs a default because an earlier parameter has one",
+                        param, name
+                    ));
                 }
-                
-                // Check for more parameters (basic implementation)
-                if !matches!(self.current_token(), Token::Identifier(_)) {
+                if parameters.iter().any(|p| p.name == param) {
+                    return Err(format!("Duplicate parameter '{}' in '{}'", param, name));
+                }
+                parameters.push(Parameter { name: param, default });
+
+                if !matches!(self.current_token(), Token::Comma) {
                     break;
                 }
+                self.advance(); // Go Over Comma
             }
         }
         
@@ -180,10 +563,13 @@ impl Parser {
         if matches!(self.current_token(), Token::Indent) {
             self.advance(); // Go Over Indent
             
-            while !matches!(self.current_token(), Token::End | Token::Dedent | Token::Eof) {
-                body.push(self.parse_statement()?);
-                self.skip_newlines();
-            }
+            // Loops around a definition do not extend into its body
+            let enclosing_loops = std::mem::replace(&mut self.loop_depth, 0);
+            self.function_depth += 1;
+            let parsed = self.parse_function_body(&mut body);
+            self.function_depth -= 1;
+            self.loop_depth = enclosing_loops;
+            parsed?;
             
             // Handle either 'end' keyword or dedent
             if matches!(self.current_token(), Token::Dedent) {
@@ -195,14 +581,76 @@ impl Parser {
             self.advance();
         }
         
-        Ok(Statement::FunctionDef(FunctionDef {
+        Ok(StatementKind::FunctionDef(FunctionDef {
             name,
             parameters,
             body,
         }))
     }
     
+    fn parse_function_body(&mut self, body: &mut Vec<Statement>) -> Result<(), String> {
+        while !matches!(self.current_token(), Token::End | Token::Dedent | Token::Eof) {
+            body.push(self.parse_statement()?);
+            self.skip_newlines();
+        }
+        Ok(())
+    }
+
+    // Precedence, lowest first:
+    //
+    //   or                         parse_or
+    //   and                        parse_and
+    //   not                        parse_not
+    //   is ..., is greater than .. parse_comparison
+    //   +  -                       parse_arithmetic
+    //   *  /  %                    parse_term
+    //   unary -                    parse_unary
+    //   ^  (right associative)     parse_power
+    //   literals, names, calls, ( ) and `.property`   parse_primary
+    //
+    // So `-2 ^ 2` is -4 and `2 ^ 3 ^ 2` is 2 ^ 9.
     fn parse_expression(&mut self) -> Result<Expression, String> {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        let mut left = self.parse_and()?;
+
+        while matches!(self.current_token(), Token::Or) {
+            self.advance();
+            let right = self.parse_and()?;
+            left = Expression::Logical(LogicalOperation {
+                left: Box::new(left),
+                operator: LogicalOperator::Or,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        let mut left = self.parse_not()?;
+
+        while matches!(self.current_token(), Token::And) {
+            self.advance();
+            let right = self.parse_not()?;
+            left = Expression::Logical(LogicalOperation {
+                left: Box::new(left),
+                operator: LogicalOperator::And,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, String> {
+        if matches!(self.current_token(), Token::Not) {
+            self.advance();
+            let operand = self.parse_not()?;
+            return Ok(Expression::Not(Box::new(operand)));
+        }
         self.parse_comparison()
     }
     
@@ -249,9 +697,9 @@ impl Parser {
     }
     
     fn parse_term(&mut self) -> Result<Expression, String> {
-        let mut left = self.parse_primary()?;
+        let mut left = self.parse_unary()?;
         
-        while matches!(self.current_token(), Token::Multiply | Token::Divide) {
+        while matches!(self.current_token(), Token::Multiply | Token::Divide | Token::Modulo) {
             let op = match self.current_token() {
                 Token::Multiply => {
                     self.advance();
@@ -261,10 +709,14 @@ impl Parser {
                     self.advance();
                     BinaryOperator::Divide
                 }
+                Token::Modulo => {
+                    self.advance();
+                    BinaryOperator::Modulo
+                }
                 _ => break,
             };
             
-            let right = self.parse_primary()?;
+            let right = self.parse_unary()?;
             left = Expression::BinaryOp(BinaryOperation {
                 left: Box::new(left),
                 operator: op,
@@ -275,6 +727,32 @@ impl Parser {
         Ok(left)
     }
 
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if matches!(self.current_token(), Token::Minus) {
+            self.advance();
+            let operand = self.parse_unary()?;
+            return Ok(Expression::Negate(Box::new(operand)));
+        }
+        self.parse_power()
+    }
+
+    fn parse_power(&mut self) -> Result<Expression, String> {
+        let base = self.parse_primary()?;
+
+        if matches!(self.current_token(), Token::Power) {
+            self.advance();
+            // The exponent may itself be negative or another power: `2 ^ -1`, `2 ^ 3 ^ 2`
+            let exponent = self.parse_unary()?;
+            return Ok(Expression::BinaryOp(BinaryOperation {
+                left: Box::new(base),
+                operator: BinaryOperator::Power,
+                right: Box::new(exponent),
+            }));
+        }
+
+        Ok(base)
+    }
+
     fn parse_comparison_operator(&mut self) -> Option<BinaryOperator> {
         match self.current_token() {
             Token::IsGreaterThan => {
@@ -301,6 +779,18 @@ impl Parser {
                 self.advance();
                 Some(BinaryOperator::NotEqual)
             }
+            Token::Contains => {
+                self.advance();
+                Some(BinaryOperator::Contains)
+            }
+            Token::StartsWith => {
+                self.advance();
+                Some(BinaryOperator::StartsWith)
+            }
+            Token::EndsWith => {
+                self.advance();
+                Some(BinaryOperator::EndsWith)
+            }
             _ => None,
         }
     }
@@ -316,12 +806,325 @@ impl Parser {
                 self.advance();
                 Ok(Expression::String(s))
             }
+            Token::InterpolatedString(parts) => {
+                self.advance();
+                let mut pieces = Vec::new();
+                for part in parts {
+                    pieces.push(match part {
+                        StringPart::Text(text) => InterpolationPart::Text(text),
+                        // Errors inside `{...}` carry their exact position
+                        StringPart::Expression { source, format, line, column } => {
+                            let expression = Self::parse_embedded_expression(&source, line, column)
+                                .map_err(|err| self.located(err))?;
+                            let format = match format {
+                                Some(spec) => Some(
+                                    Self::parse_format_spec(&spec, line, column).map_err(|err| self.located(err))?,
+                                ),
+                                None => None,
+                            };
+                            InterpolationPart::Value { expression, format }
+                        }
+                    });
+                }
+                Ok(Expression::Interpolation(pieces))
+            }
+            Token::True | Token::False => {
+                let value = matches!(self.current_token(), Token::True);
+                self.advance();
+                Ok(Expression::Boolean(value))
+            }
+            Token::Nothing => {
+                self.advance();
+                Ok(Expression::Nothing)
+            }
             Token::Identifier(name) => {
                 self.advance();
-                // Check if this is a function call (basic implementation)
-                Ok(Expression::Identifier(name))
+                // A name directly followed by a value is a call: `greet "Bob"`, `math.power 2, 3`
+                let callee = self.parse_property_chain(Expression::Identifier(name))?;
+                if self.starts_argument() {
+                    let arguments = self.parse_arguments()?;
+                    Ok(Expression::FunctionCall(FunctionCall { callee: Box::new(callee), arguments }))
+                } else {
+                    Ok(callee)
+                }
+            }
+            Token::LeftParen => {
+                self.advance(); // Go Over LeftParen
+                let inner = self.parse_expression()?;
+                self.expect(Token::RightParen)?;
+                self.parse_property_chain(inner)
+            }
+            Token::LeftBrace => {
+                self.advance(); // Go Over LeftBrace
+                let mut properties: Vec<(String, Expression)> = Vec::new();
+                while !matches!(self.current_token(), Token::RightBrace) {
+                    let key = match self.current_token() {
+                        Token::Identifier(key) | Token::String(key) => key.clone(),
+                        other => return Err(format!("Expected property name in object, found {:?}", other)),
+                    };
+                    self.advance();
+                    if properties.iter().any(|(existing, _)| *existing == key) {
+                        return Err(format!("Duplicate property '{}' in object", key));
+                    }
+                    self.expect(Token::Colon)?;
+                    properties.push((key, self.parse_expression()?));
+
+                    if !matches!(self.current_token(), Token::Comma) {
+                        break;
+                    }
+                    self.advance(); // Go Over Comma
+                }
+                self.expect(Token::RightBrace)?;
+                self.parse_property_chain(Expression::Object(properties))
+            }
+            Token::LeftBracket => {
+                self.advance(); // Go Over LeftBracket
+                let mut items = Vec::new();
+                if !matches!(self.current_token(), Token::RightBracket) {
+                    items.push(self.parse_expression()?);
+                    while matches!(self.current_token(), Token::Comma) {
+                        self.advance(); // Go Over Comma
+                        items.push(self.parse_expression()?);
+                    }
+                }
+                self.expect(Token::RightBracket)?;
+                self.parse_property_chain(Expression::List(items))
+            }
+            Token::Length | Token::First | Token::Last => {
+                let kind = match self.current_token() {
+                    Token::Length => ListQueryKind::Length,
+                    Token::First => ListQueryKind::First,
+                    _ => ListQueryKind::Last,
+                };
+                self.advance();
+                self.expect(Token::Of)?;
+                let list = self.parse_primary()?;
+                Ok(Expression::ListQuery(ListQuery { kind, list: Box::new(list) }))
+            }
+            Token::Ask => {
+                self.advance(); // Go Over Ask
+                // `number` is only special straight after `ask`
+                let numeric = matches!(self.current_token(), Token::Identifier(name) if name == "number");
+                if numeric {
+                    self.advance();
+                }
+                let prompt = if self.starts_argument() {
+                    Some(Box::new(self.parse_primary()?))
+                } else {
+                    None
+                };
+                Ok(Expression::Ask(Ask { numeric, prompt }))
             }
             _ => Err(format!("Unexpected token in expression: {:?}", self.current_token())),
         }
     }
-}
\ No newline at end of file
+
+    fn located(&mut self, err: String) -> String {
+        self.error_located = true;
+        err
+    }
+
+    // Lexes and parses the source of one `{...}` in a string. The lexer starts at the
+    // expression's real position, so errors point into the original file.
+    fn parse_embedded_expression(source: &str, line: usize, column: usize) -> Result<Expression, String> {
+        let tokens = Lexer::new(source).starting_at(line, column).tokenize()?;
+        let mut parser = Parser::new(tokens);
+        let located = |parser: &Parser, err: String| {
+            let token = parser.tokens.get(parser.current).or(parser.tokens.last());
+            let (line, column) = token.map(|t| (t.line, t.column)).unwrap_or((line, column));
+            format!("{} in string interpolation at line {}, column {}", err, line, column)
+        };
+
+        let expression = match parser.parse_expression() {
+            Ok(expression) => expression,
+            Err(err) => return Err(located(&parser, err)),
+        };
+        if !matches!(parser.current_token(), Token::Eof) {
+            let err = format!("Unexpected token {:?}", parser.current_token());
+            return Err(located(&parser, err));
+        }
+        Ok(expression)
+    }
+
+    fn parse_format_spec(spec: &str, line: usize, column: usize) -> Result<FormatSpec, String> {
+        let invalid = || format!("Invalid format '{}' in string at line {}, column {}", spec, line, column);
+        let alignment = |c: char| match c {
+            '<' => Some(Alignment::Left),
+            '>' => Some(Alignment::Right),
+            '^' => Some(Alignment::Center),
+            _ => None,
+        };
+
+        let chars: Vec<char> = spec.chars().collect();
+        let mut format = FormatSpec {
+            fill: ' ',
+            align: None,
+            width: 0,
+            precision: None,
+            percent: false,
+        };
+        let mut i = 0;
+
+        // An alignment may be preceded by a fill character: `*^9`
+        if let Some(align) = chars.get(1).and_then(|c| alignment(*c)) {
+            format.fill = chars[0];
+            format.align = Some(align);
+            i = 2;
+        } else if let Some(align) = chars.first().and_then(|c| alignment(*c)) {
+            format.align = Some(align);
+            i = 1;
+        }
+
+        // `None` when there are no digits; a number too large to use is an error
+        let digits = |i: &mut usize| {
+            let start = *i;
+            while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+                *i += 1;
+            }
+            if start == *i {
+                return Ok(None);
+            }
+            chars[start..*i].iter().collect::<String>().parse::<usize>().map(Some).map_err(|_| invalid())
+        };
+        format.width = digits(&mut i)?.unwrap_or(0);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            format.precision = Some(digits(&mut i)?.ok_or_else(invalid)?);
+        }
+        if chars.get(i) == Some(&'%') {
+            format.percent = true;
+            i += 1;
+        }
+
+        if i != chars.len() {
+            return Err(invalid());
+        }
+        Ok(format)
+    }
+
+    // `user.address.city`
+    fn parse_property_chain(&mut self, mut expression: Expression) -> Result<Expression, String> {
+        while matches!(self.current_token(), Token::Dot) {
+            self.advance(); // Go Over Dot
+            let property = match self.current_token() {
+                Token::Identifier(property) => property.clone(),
+                other => return Err(format!("Expected property name after '.', found {:?}", other)),
+            };
+            self.advance();
+            expression = Expression::Property(PropertyAccess {
+                object: Box::new(expression),
+                property,
+            });
+        }
+        Ok(expression)
+    }
+
+    fn starts_argument(&self) -> bool {
+        matches!(
+            self.current_token(),
+            Token::Number(_)
+                | Token::String(_)
+                | Token::InterpolatedString(_)
+                | Token::True
+                | Token::False
+                | Token::Nothing
+                | Token::Identifier(_)
+                | Token::LeftParen
+                | Token::LeftBracket
+                | Token::LeftBrace
+                | Token::Length
+                | Token::First
+                | Token::Last
+                | Token::Ask
+        )
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<Expression>, String> {
+        let mut arguments = vec![self.parse_arithmetic()?];
+        while matches!(self.current_token(), Token::Comma) {
+            self.advance(); // Go Over Comma
+            arguments.push(self.parse_arithmetic()?);
+        }
+        Ok(arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Program, String> {
+        Parser::new(Lexer::new(source).tokenize()?).parse()
+    }
+
+    fn imported_names(source: &str) -> Vec<ImportedName> {
+        match parse(source).unwrap().statements.remove(0).kind {
+            StatementKind::Import(import) => import.names,
+            other => panic!("expected an import, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn imported_names_can_be_renamed() {
+        let names = imported_names("import power as raise, floor from \"math\"\n");
+        let bound: Vec<&str> = names.iter().map(ImportedName::bound).collect();
+        assert_eq!(names[0].name, "power");
+        assert_eq!(bound, ["raise", "floor"]);
+    }
+
+    #[test]
+    fn keywords_can_be_imported_under_another_name() {
+        let names = imported_names("import length as text_length, repeat as repeat_text from \"string_utils\"\n");
+        let pairs: Vec<(&str, &str)> = names.iter().map(|name| (name.name.as_str(), name.bound())).collect();
+        assert_eq!(pairs, [("length", "text_length"), ("repeat", "repeat_text")]);
+
+        let error = parse("import length from \"string_utils\"\n").unwrap_err();
+        assert!(error.starts_with("'length' is a keyword"), "{}", error);
+    }
+
+    #[test]
+    fn errors_get_the_line_of_their_statement() {
+        let error = parse("let x be 1\nlet y \"meet at line 5\"\n").unwrap_err();
+        assert_eq!(error, "Expected Be, found String(\"meet at line 5\") at line 2");
+    }
+
+    #[test]
+    fn errors_in_string_interpolation_keep_their_own_position() {
+        let error = parse("let x be 1\nshow \"total: {x +}\"\n").unwrap_err();
+        assert!(error.ends_with("in string interpolation at line 2, column 17"), "{}", error);
+    }
+
+    fn expression(source: &str) -> Expression {
+        match parse(source).unwrap().statements.remove(0).kind {
+            StatementKind::Expression(expression) => expression,
+            other => panic!("expected an expression, got {:?}", other),
+        }
+    }
+
+    fn call(callee: &str, arguments: Vec<Expression>) -> Expression {
+        Expression::FunctionCall(FunctionCall { callee: Box::new(Expression::Identifier(callee.to_string())), arguments })
+    }
+
+    #[test]
+    fn a_minus_after_a_name_subtracts() {
+        let subtraction = Expression::BinaryOp(BinaryOperation {
+            left: Box::new(Expression::Identifier("f".to_string())),
+            operator: BinaryOperator::Subtract,
+            right: Box::new(Expression::Number(3.0)),
+        });
+        assert_eq!(expression("f -3\n"), subtraction);
+
+        let minus_three = Expression::Negate(Box::new(Expression::Number(3.0)));
+        assert_eq!(expression("f (-3)\n"), call("f", vec![minus_three.clone()]));
+        assert_eq!(expression("f 1, -3\n"), call("f", vec![Expression::Number(1.0), minus_three]));
+    }
+
+    #[test]
+    fn format_numbers_too_large_to_use_are_invalid() {
+        assert!(parse("let x be 1\nshow \"{x:>5.2}\"\n").is_ok());
+        for spec in ["99999999999999999999", ".99999999999999999999"] {
+            let error = parse(&format!("let x be 1\nshow \"{{x:{}}}\"\n", spec)).unwrap_err();
+            assert_eq!(error, format!("Invalid format '{}' in string at line 2, column 7", spec));
+        }
+    }
+}
@@ -0,0 +1,41 @@
+// Helpers shared by the tests of several modules.
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// A fresh directory under the system's temporary directory. It is removed when the
+// value is dropped, so a failing assertion does not leave it behind.
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("delta-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ScratchDir(fs::canonicalize(dir).unwrap())
+    }
+
+    pub fn with_files(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = ScratchDir::new(name);
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+}
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
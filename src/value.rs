@@ -0,0 +1,356 @@
+use crate::ast::{Alignment, FormatSpec, FunctionDef};
+use crate::error::{ErrorKind, RuntimeError};
+use crate::library::Host;
+use crate::library::date_time::{DateTime, Duration};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+// Runtime values produced by the interpreter.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Nothing,
+    // Lists are shared, so changes made through one name are seen through all of them
+    List(Rc<RefCell<Vec<Value>>>),
+    Object(Rc<RefCell<Object>>),
+    Function(Rc<Function>),
+    Builtin(Rc<Builtin>),
+    // A caught error, as bound by `rescue`
+    Error(Rc<RuntimeError>),
+    Time(DateTime),
+    Duration(Duration),
+}
+
+// A function remembers the module it was defined in, so its body sees that module's
+// globals wherever it is called from.
+pub struct Function {
+    pub def: FunctionDef,
+    pub module: Rc<RefCell<Module>>,
+}
+
+// The module refers back to its functions, so only the name is printed.
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Function({})", self.def.name)
+    }
+}
+
+pub type BuiltinFn = fn(&mut Host, Vec<Value>) -> Result<Value, RuntimeError>;
+
+// A function from a built-in library. The argument count is checked before `run` is called.
+pub struct Builtin {
+    pub name: &'static str,
+    pub min_args: usize,
+    // `None` when any number of arguments is accepted
+    pub max_args: Option<usize>,
+    pub run: BuiltinFn,
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Builtin({})", self.name)
+    }
+}
+
+// The top-level names of the program file or of one imported module.
+#[derive(Debug, Default)]
+pub struct Module {
+    pub variables: HashMap<String, Value>,
+    // Names declared with `const`, with the line of their declaration
+    pub constants: HashMap<String, usize>,
+    // The file of an imported module, shown in its errors. Nothing for the program itself.
+    pub file: Option<String>,
+}
+
+// Properties keep the order they were written in.
+#[derive(Debug, Clone, Default)]
+pub struct Object {
+    properties: Vec<(String, Value)>,
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Object::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.properties.iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    pub fn insert(&mut self, key: String, value: Value) {
+        match self.properties.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.properties.push((key, value)),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.properties.iter().map(|(name, _)| name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.properties.iter().map(|(name, value)| (name, value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.properties.len()
+    }
+}
+
+// Two objects are equal when they have the same properties, in any order.
+impl PartialEq for Object {
+    fn eq(&self, other: &Object) -> bool {
+        self.equals(other, &mut Vec::new())
+    }
+}
+
+impl Object {
+    fn equals(&self, other: &Object, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(key, value)| other.get(key).is_some_and(|found| value.equals(found, comparing)))
+    }
+}
+
+impl Value {
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn object(object: Object) -> Value {
+        Value::Object(Rc::new(RefCell::new(object)))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::Nothing => "nothing",
+            Value::List(_) => "list",
+            Value::Object(_) => "object",
+            Value::Function(_) | Value::Builtin(_) => "function",
+            Value::Error(_) => "error",
+            Value::Time(_) => "time",
+            Value::Duration(_) => "duration",
+        }
+    }
+
+    // How the value reads inside a list or an error message. Strings keep their quotes,
+    // so `["a, b"]` and `["a", "b"]` print differently.
+    pub fn repr(&self) -> String {
+        match self {
+            Value::String(s) => format!("\"{}\"", s),
+            other => other.to_string(),
+        }
+    }
+
+    // Applies a `{value:spec}` format. Precision and `%` only make sense for numbers;
+    // width and alignment work for anything, counting characters rather than bytes.
+    pub fn format(&self, spec: &FormatSpec) -> Result<String, RuntimeError> {
+        let text = match self {
+            Value::Number(n) => {
+                let n = if spec.percent { n * 100.0 } else { *n };
+                let text = match spec.precision {
+                    Some(precision) => format!("{:.*}", precision, n),
+                    None => n.to_string(),
+                };
+                if spec.percent { text + "%" } else { text }
+            }
+            other if spec.precision.is_some() || spec.percent => {
+                return Err(RuntimeError::new(
+                    ErrorKind::Type,
+                    format!("Type error: number format used on a {}", other.type_name()),
+                ))
+            }
+            other => other.to_string(),
+        };
+
+        let padding = spec.width.saturating_sub(text.chars().count());
+        let default = if matches!(self, Value::Number(_)) { Alignment::Right } else { Alignment::Left };
+        let (before, after) = match spec.align.unwrap_or(default) {
+            Alignment::Left => (0, padding),
+            Alignment::Right => (padding, 0),
+            Alignment::Center => (padding / 2, padding - padding / 2),
+        };
+        let fill = |count: usize| spec.fill.to_string().repeat(count);
+        Ok(format!("{}{}{}", fill(before), text, fill(after)))
+    }
+
+    // Ordering is only defined between two values of the same kind: numbers, strings,
+    // times or durations.
+    pub fn compare(&self, other: &Value) -> Result<Ordering, RuntimeError> {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a
+                .partial_cmp(b)
+                .ok_or_else(|| RuntimeError::new(ErrorKind::Value, "Cannot compare NaN")),
+            (Value::String(a), Value::String(b)) => Ok(a.cmp(b)),
+            (Value::Time(a), Value::Time(b)) => Ok(a.0.cmp(&b.0)),
+            (Value::Duration(a), Value::Duration(b)) => Ok(a.0.cmp(&b.0)),
+            _ => Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Type error: cannot compare {} with {}", self.type_name(), other.type_name()),
+            )),
+        }
+    }
+}
+
+// Values of different types are never equal.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.equals(other, &mut Vec::new())
+    }
+}
+
+impl Value {
+    // `comparing` holds the pairs of lists and objects whose comparison is under way. Meeting
+    // a pair again means the values contain themselves in the same places, and any
+    // difference will show up elsewhere, so the pair counts as equal.
+    fn equals(&self, other: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Nothing, Value::Nothing) => true,
+            (Value::List(a), Value::List(b)) => {
+                let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if Rc::ptr_eq(a, b) || comparing.contains(&pair) {
+                    return true;
+                }
+                comparing.push(pair);
+                let (a, b) = (a.borrow(), b.borrow());
+                let equal = a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y, comparing));
+                comparing.pop();
+                equal
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if Rc::ptr_eq(a, b) || comparing.contains(&pair) {
+                    return true;
+                }
+                comparing.push(pair);
+                let equal = a.borrow().equals(&b.borrow(), comparing);
+                comparing.pop();
+                equal
+            }
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Builtin(a), Value::Builtin(b)) => Rc::ptr_eq(a, b),
+            (Value::Error(a), Value::Error(b)) => a == b,
+            (Value::Time(a), Value::Time(b)) => a == b,
+            (Value::Duration(a), Value::Duration(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    // `open` holds the lists and objects being written, as in the JSON writer, so one
+    // that contains itself prints as `[...]` or `{...}` there instead of forever.
+    fn write(&self, f: &mut fmt::Formatter, open: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::List(items) => {
+                let id = Rc::as_ptr(items) as *const ();
+                if open.contains(&id) {
+                    return write!(f, "[...]");
+                }
+                open.push(id);
+                write!(f, "[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write_item(f, open)?;
+                }
+                open.pop();
+                write!(f, "]")
+            }
+            Value::Object(object) => {
+                let id = Rc::as_ptr(object) as *const ();
+                if open.contains(&id) {
+                    return write!(f, "{{...}}");
+                }
+                let object = object.borrow();
+                if object.len() == 0 {
+                    return write!(f, "{{}}");
+                }
+                open.push(id);
+                write!(f, "{{ ")?;
+                for (i, (key, value)) in object.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key)?;
+                    value.write_item(f, open)?;
+                }
+                open.pop();
+                write!(f, " }}")
+            }
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Nothing => write!(f, "nothing"),
+            Value::Function(function) => write!(f, "<function {}>", function.def.name),
+            Value::Builtin(builtin) => write!(f, "<function {}>", builtin.name),
+            // Just the message, so `"Could not save: " + error` reads naturally
+            Value::Error(error) => write!(f, "{}", error.message),
+            Value::Time(time) => write!(f, "{}", time),
+            Value::Duration(duration) => write!(f, "{}", duration),
+        }
+    }
+
+    // An item inside a list or object, written the way `repr` does.
+    fn write_item(&self, f: &mut fmt::Formatter, open: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "\"{}\"", s),
+            other => other.write(f, open),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `[1, <itself>]`
+    fn list_containing_itself() -> Value {
+        let list = Value::list(vec![Value::Number(1.0)]);
+        if let Value::List(items) = &list {
+            items.borrow_mut().push(list.clone());
+        }
+        list
+    }
+
+    #[test]
+    fn values_that_contain_themselves_print_a_placeholder() {
+        assert_eq!(list_containing_itself().to_string(), "[1, [...]]");
+
+        let object = Value::object(Object::new());
+        if let Value::Object(properties) = &object {
+            properties.borrow_mut().insert("me".to_string(), object.clone());
+        }
+        assert_eq!(object.to_string(), "{ me: {...} }");
+    }
+
+    #[test]
+    fn values_that_contain_themselves_can_be_compared() {
+        assert!(list_containing_itself() == list_containing_itself());
+        assert!(list_containing_itself() != Value::list(vec![Value::Number(1.0), Value::Number(1.0)]));
+    }
+
+    #[test]
+    fn equality_agrees_with_ordering() {
+        let sum = Value::Number(0.1 + 0.2);
+        let expected = Value::Number(0.3);
+        assert!(sum != expected);
+        assert_eq!(sum.compare(&expected).unwrap(), Ordering::Greater);
+        assert!(Value::Number(0.5 + 0.25) == Value::Number(0.75));
+    }
+}